@@ -0,0 +1,285 @@
+//! Turning gateway events other than new messages into inputs for the model.
+
+use crate::State;
+use crate::render;
+use crate::session::Input;
+use serde::Deserialize;
+use twilight_gateway::Event;
+use twilight_model::channel::Message;
+use twilight_model::channel::message::EmojiReactionType;
+use twilight_model::gateway::GatewayReaction;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, UserMarker};
+
+/// What happens with a kind of event.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventMode {
+    Ignore,
+    /// Add it to the context of a session that is already open, without having the model respond.
+    Context,
+    /// Have the model respond to it.
+    Respond,
+}
+
+/// How events are forwarded to the model, messages by ignored authors are never forwarded.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EventOptions {
+    #[serde(default = "default_context")]
+    pub message_update: EventMode,
+    #[serde(default = "default_context")]
+    pub message_delete: EventMode,
+    #[serde(default = "default_context")]
+    pub reaction_add: EventMode,
+    #[serde(default = "default_ignore")]
+    pub reaction_remove: EventMode,
+    /// Also forward added reactions to messages not written by ari.
+    #[serde(default)]
+    pub all_reactions: bool,
+    #[serde(default = "default_context")]
+    pub thread_create: EventMode,
+    /// Posted to the system channel of the guild.
+    #[serde(default = "default_ignore")]
+    pub member_add: EventMode,
+}
+
+impl Default for EventOptions {
+    fn default() -> Self {
+        Self {
+            message_update: EventMode::Context,
+            message_delete: EventMode::Context,
+            reaction_add: EventMode::Context,
+            reaction_remove: EventMode::Ignore,
+            all_reactions: false,
+            thread_create: EventMode::Context,
+            member_add: EventMode::Ignore,
+        }
+    }
+}
+
+fn default_context() -> EventMode {
+    EventMode::Context
+}
+
+fn default_ignore() -> EventMode {
+    EventMode::Ignore
+}
+
+/// An event rendered for the model, along with where it happened.
+pub struct Forward {
+    pub guild_id: Option<Id<GuildMarker>>,
+    pub channel_id: Id<ChannelMarker>,
+    pub input: Input,
+}
+
+impl Forward {
+    fn new(
+        mode: EventMode,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
+        user_id: Option<Id<UserMarker>>,
+        text: String,
+    ) -> Option<Self> {
+        let mut input = Input::text(text).in_channel(channel_id);
+
+        if let Some(user_id) = user_id {
+            input = input.by(user_id, []);
+        }
+
+        match mode {
+            EventMode::Ignore => return None,
+            EventMode::Context => input = input.ambient(),
+            EventMode::Respond => {}
+        }
+
+        Some(Self {
+            guild_id,
+            channel_id,
+            input,
+        })
+    }
+}
+
+/// Render `event` if it should be forwarded to the model.
+pub fn forward(
+    state: &State,
+    current_user_id: Id<UserMarker>,
+    event: &Event,
+) -> anyhow::Result<Option<Forward>> {
+    let options = &state.options.events;
+
+    let forward = match event {
+        Event::MessageUpdate(update) => {
+            let message = &update.0;
+
+            // embeds being resolved also update a message, without it being edited
+            if message.edited_timestamp.is_none() || !accepts(state, current_user_id, message) {
+                return Ok(None);
+            }
+
+            Forward::new(
+                options.message_update,
+                message.guild_id,
+                message.channel_id,
+                Some(message.author.id),
+                render::edited_message(state, message)?,
+            )
+        }
+        Event::MessageDelete(delete) => Forward::new(
+            options.message_delete,
+            delete.guild_id,
+            delete.channel_id,
+            None,
+            format!(
+                "Message message_id={} in #{} was deleted {}",
+                delete.id,
+                render::channel_name(state, delete.channel_id),
+                render::now()?,
+            ),
+        ),
+        Event::ReactionAdd(reaction) => {
+            let own = reaction.message_author_id == Some(current_user_id);
+
+            if !accepts_reaction(state, current_user_id, reaction)
+                || !(own || options.all_reactions)
+            {
+                return Ok(None);
+            }
+
+            let target = if own { "your message" } else { "message" };
+
+            Forward::new(
+                options.reaction_add,
+                reaction.guild_id,
+                reaction.channel_id,
+                Some(reaction.user_id),
+                format!(
+                    "{} reacted {} to {target} message_id={} in #{} {}",
+                    reactor(state, reaction),
+                    emoji(&reaction.emoji),
+                    reaction.message_id,
+                    render::channel_name(state, reaction.channel_id),
+                    render::now()?,
+                ),
+            )
+        }
+        Event::ReactionRemove(reaction) => {
+            if !accepts_reaction(state, current_user_id, reaction) {
+                return Ok(None);
+            }
+
+            Forward::new(
+                options.reaction_remove,
+                reaction.guild_id,
+                reaction.channel_id,
+                Some(reaction.user_id),
+                format!(
+                    "{} removed their {} reaction from message_id={} in #{} {}",
+                    reactor(state, reaction),
+                    emoji(&reaction.emoji),
+                    reaction.message_id,
+                    render::channel_name(state, reaction.channel_id),
+                    render::now()?,
+                ),
+            )
+        }
+        Event::ThreadCreate(thread) => {
+            if thread.newly_created != Some(true) || thread.owner_id == Some(current_user_id) {
+                return Ok(None);
+            }
+
+            let owner = thread
+                .owner_id
+                .and_then(|owner_id| render::user_name(state, thread.guild_id, owner_id))
+                .unwrap_or_else(|| String::from("someone"));
+
+            let parent = thread
+                .parent_id
+                .map(|parent_id| render::channel_name(state, parent_id))
+                .unwrap_or_else(|| String::from("unknown"));
+
+            Forward::new(
+                options.thread_create,
+                thread.guild_id,
+                thread.id,
+                thread.owner_id,
+                format!(
+                    "{owner} created the thread #{} channel_id={} in #{parent} {}",
+                    thread.name.as_deref().unwrap_or("unknown"),
+                    thread.id,
+                    render::now()?,
+                ),
+            )
+        }
+        Event::MemberAdd(member) => {
+            let Some(channel_id) = state
+                .cache
+                .guild(member.guild_id)
+                .and_then(|guild| guild.system_channel_id())
+            else {
+                return Ok(None);
+            };
+
+            let user = &member.member.user;
+
+            if user.bot && !state.options.trigger.accepts_bot(user.id) {
+                return Ok(None);
+            }
+
+            Forward::new(
+                options.member_add,
+                Some(member.guild_id),
+                channel_id,
+                Some(user.id),
+                format!(
+                    "{} (@{}, user_id={}) joined the server {}",
+                    user.global_name.as_deref().unwrap_or(&user.name),
+                    user.name,
+                    user.id,
+                    render::now()?,
+                ),
+            )
+        }
+        _ => None,
+    };
+
+    Ok(forward)
+}
+
+/// Whether the author of `message` is one the trigger options accept.
+fn accepts(state: &State, current_user_id: Id<UserMarker>, message: &Message) -> bool {
+    message.author.id != current_user_id && state.options.trigger.accepts(message)
+}
+
+/// Whether `reaction` was not added by ari or an ignored bot.
+fn accepts_reaction(
+    state: &State,
+    current_user_id: Id<UserMarker>,
+    reaction: &GatewayReaction,
+) -> bool {
+    let bot = reaction
+        .member
+        .as_ref()
+        .is_some_and(|member| member.user.bot);
+
+    reaction.user_id != current_user_id
+        && (!bot || state.options.trigger.accepts_bot(reaction.user_id))
+}
+
+fn reactor(state: &State, reaction: &GatewayReaction) -> String {
+    reaction
+        .member
+        .as_ref()
+        .and_then(|member| member.nick.clone())
+        .or_else(|| render::user_name(state, reaction.guild_id, reaction.user_id))
+        .unwrap_or_else(|| format!("user_id={}", reaction.user_id))
+}
+
+fn emoji(emoji: &EmojiReactionType) -> String {
+    match emoji {
+        EmojiReactionType::Custom { name, .. } => {
+            format!(":{}:", name.as_deref().unwrap_or("unknown"))
+        }
+        EmojiReactionType::Unicode { name } => name.clone(),
+    }
+}
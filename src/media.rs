@@ -0,0 +1,360 @@
+//! Turning attachments, embeds and stickers into parts the model can see and hear.
+
+use crate::State;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::part::Data;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{Blob, Part};
+use anyhow::Context;
+use serde::Deserialize;
+use std::iter;
+use std::time::Duration;
+use tokio::task;
+use tracing::{debug, warn};
+use twilight_model::channel::Message;
+use twilight_model::channel::message::MessageFlags;
+use twilight_model::channel::message::sticker::StickerFormatType;
+
+mod animation;
+pub mod audio;
+mod image;
+
+/// Limits on what is downloaded and sent to the model.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MediaOptions {
+    /// Images are downscaled so neither side exceeds this many pixels.
+    #[serde(default = "default_max_dimension")]
+    pub max_dimension: u32,
+    /// Attachments larger than this many bytes are not downloaded.
+    #[serde(default = "default_max_size")]
+    pub max_size: u64,
+    /// Images sent per message, any further images are skipped.
+    #[serde(default = "default_max_images")]
+    pub max_images: usize,
+    /// Frames sampled from each animated GIF, WebP or PNG.
+    #[serde(default = "default_max_frames")]
+    pub max_frames: usize,
+    /// Tile sampled frames into a single image rather than sending each of them.
+    #[serde(default = "default_contact_sheet")]
+    pub contact_sheet: bool,
+    /// Seconds of each audio attachment or voice message sent, the rest is cut off.
+    #[serde(default = "default_max_audio_duration")]
+    pub max_audio_duration: u64,
+    /// Download media of messages the model only reads along with, rather than just naming it.
+    #[serde(default)]
+    pub ambient: bool,
+}
+
+impl Default for MediaOptions {
+    fn default() -> Self {
+        Self {
+            max_dimension: default_max_dimension(),
+            max_size: default_max_size(),
+            max_images: default_max_images(),
+            max_frames: default_max_frames(),
+            contact_sheet: default_contact_sheet(),
+            max_audio_duration: default_max_audio_duration(),
+            ambient: false,
+        }
+    }
+}
+
+fn default_max_dimension() -> u32 {
+    1024
+}
+
+fn default_max_size() -> u64 {
+    20 * 1024 * 1024
+}
+
+fn default_max_images() -> usize {
+    4
+}
+
+fn default_max_frames() -> usize {
+    4
+}
+
+fn default_contact_sheet() -> bool {
+    true
+}
+
+fn default_max_audio_duration() -> u64 {
+    120
+}
+
+impl MediaOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.max_dimension == 0 {
+            anyhow::bail!("media.max_dimension must be at least 1");
+        }
+
+        if self.max_frames == 0 {
+            anyhow::bail!("media.max_frames must be at least 1");
+        }
+
+        if self.max_audio_duration == 0 {
+            anyhow::bail!("media.max_audio_duration must be at least 1");
+        }
+
+        Ok(())
+    }
+}
+
+/// An image or audio posted with a message, as an attachment, embed or sticker.
+#[derive(Clone, Debug)]
+pub struct Media {
+    pub kind: MediaKind,
+    /// What the model is told the media is.
+    pub label: String,
+    pub url: String,
+    /// Size in bytes, if known before downloading.
+    pub size: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MediaKind {
+    Image,
+    /// Any audio, of which Ogg Opus, as voice messages are recorded, and WAV can be played.
+    Audio,
+}
+
+/// The images and audio of `message`, in the order they are shown.
+///
+/// Animated gif attachments and stickers keep their frames, gif embeds are only a still.
+pub fn collect(message: &Message) -> Vec<Media> {
+    let voice_message = message
+        .flags
+        .is_some_and(|flags| flags.contains(MessageFlags::IS_VOICE_MESSAGE));
+
+    let attachments = message.attachments.iter().filter_map(|attachment| {
+        let content_type = attachment.content_type.as_deref()?;
+
+        let (kind, label) = if content_type.starts_with("image/") {
+            (
+                MediaKind::Image,
+                format!("Attachment {}", attachment.filename),
+            )
+        } else if content_type.starts_with("audio/") {
+            let label = if voice_message {
+                String::from("Voice message")
+            } else {
+                format!("Attachment {}", attachment.filename)
+            };
+
+            (MediaKind::Audio, label)
+        } else {
+            return None;
+        };
+
+        Some(Media {
+            kind,
+            label,
+            url: attachment.url.clone(),
+            size: Some(attachment.size),
+        })
+    });
+
+    // gif embeds such as Tenor and Giphy carry the animation only as a video, which cannot be
+    // decoded, so the model sees their thumbnail, a still, and is told as much
+    let embeds = message.embeds.iter().filter_map(|embed| {
+        let url = embed
+            .image
+            .as_ref()
+            .map(|image| image.proxy_url.as_ref().unwrap_or(&image.url))
+            .or_else(|| {
+                embed
+                    .thumbnail
+                    .as_ref()
+                    .map(|thumbnail| thumbnail.proxy_url.as_ref().unwrap_or(&thumbnail.url))
+            })?;
+
+        let source = embed
+            .provider
+            .as_ref()
+            .and_then(|provider| provider.name.as_deref())
+            .or(embed.title.as_deref())
+            .unwrap_or("a link");
+
+        let label = if embed.kind == "gifv" {
+            format!("Still of an animated embed from {source}")
+        } else {
+            format!("Embed from {source}")
+        };
+
+        Some(Media {
+            kind: MediaKind::Image,
+            label,
+            url: url.clone(),
+            size: None,
+        })
+    });
+
+    let stickers = message.sticker_items.iter().filter_map(|sticker| {
+        let extension = match sticker.format_type {
+            StickerFormatType::Png | StickerFormatType::Apng => "png",
+            StickerFormatType::Gif => "gif",
+            _ => return None,
+        };
+
+        Some(Media {
+            kind: MediaKind::Image,
+            label: format!("Sticker {}", sticker.name),
+            url: format!(
+                "https://media.discordapp.net/stickers/{}.{extension}",
+                sticker.id
+            ),
+            size: None,
+        })
+    });
+
+    attachments.chain(embeds).chain(stickers).collect()
+}
+
+/// Download and convert `media` into parts, each preceded by a part naming it.
+///
+/// Media that is too large, fails to download or fails to convert is skipped.
+pub async fn parts(state: &State, media: &[Media]) -> Vec<Part> {
+    let options = &state.options.media;
+    let mut parts = Vec::new();
+
+    let images = media
+        .iter()
+        .filter(|media| media.kind == MediaKind::Image)
+        .take(options.max_images);
+
+    let audio = media.iter().filter(|media| media.kind == MediaKind::Audio);
+
+    for media in images.chain(audio) {
+        if let Some(size) = media.size.filter(|size| *size > options.max_size) {
+            debug!(
+                "skip {} of {size} bytes, larger than media.max_size",
+                media.label
+            );
+
+            continue;
+        }
+
+        let converted = match media.kind {
+            MediaKind::Image => image_parts(state, media).await,
+            MediaKind::Audio => audio_parts(state, media).await,
+        };
+
+        match converted {
+            Ok(converted) => parts.extend(converted),
+            Err(error) => warn!("failed to convert {}: {error}", media.label),
+        }
+    }
+
+    parts
+}
+
+/// Parts naming `media` without downloading it.
+pub fn labels(media: &[Media]) -> Vec<Part> {
+    media
+        .iter()
+        .map(|media| text(format!("{} (not shown)", media.label)))
+        .collect()
+}
+
+async fn image_parts(state: &State, media: &Media) -> anyhow::Result<Vec<Part>> {
+    let options = &state.options.media;
+    let converted = convert_image(state, media).await?;
+
+    let label = match converted.frames {
+        Some(frames) if options.contact_sheet => format!(
+            "{} (animated, {frames} frames, {} sampled in order, left to right and top to bottom):",
+            media.label,
+            options.max_frames.min(frames),
+        ),
+        Some(frames) => format!(
+            "{} (animated, {frames} frames, {} sampled in order):",
+            media.label,
+            converted.blobs.len(),
+        ),
+        None => format!("{}:", media.label),
+    };
+
+    Ok(iter::once(text(label))
+        .chain(converted.blobs.into_iter().map(inline_data))
+        .collect())
+}
+
+async fn audio_parts(state: &State, media: &Media) -> anyhow::Result<Vec<Part>> {
+    let bytes = download(state, &media.url).await?;
+    let max_duration = Duration::from_secs(state.options.media.max_audio_duration);
+
+    let converted = task::spawn_blocking(move || audio::convert(&bytes, max_duration))
+        .await
+        .context("audio conversion panicked")?;
+
+    // otherwise the model would answer as if the audio was never posted
+    let converted = match converted {
+        Ok(converted) => converted,
+        Err(error) => {
+            warn!("failed to convert {}: {error}", media.label);
+
+            return Ok(vec![text(format!(
+                "{} (could not be played: {error})",
+                media.label
+            ))]);
+        }
+    };
+
+    let label = if converted.truncated {
+        format!(
+            "{} (cut off after {} seconds):",
+            media.label,
+            max_duration.as_secs()
+        )
+    } else {
+        format!(
+            "{} ({:.1} seconds):",
+            media.label,
+            converted.duration.as_secs_f64()
+        )
+    };
+
+    Ok(vec![text(label), inline_data(converted.blob)])
+}
+
+fn text(text: String) -> Part {
+    Part {
+        data: Some(Data::Text(text)),
+    }
+}
+
+fn inline_data(blob: Blob) -> Part {
+    Part {
+        data: Some(Data::InlineData(blob)),
+    }
+}
+
+/// Download `url`, refusing bodies larger than `media.max_size`.
+async fn download(state: &State, url: &str) -> anyhow::Result<Vec<u8>> {
+    let max_size = state.options.media.max_size;
+
+    let response = state.client.get(url).send().await?.error_for_status()?;
+
+    if response
+        .content_length()
+        .is_some_and(|size| size > max_size)
+    {
+        anyhow::bail!("larger than media.max_size");
+    }
+
+    let bytes = response.bytes().await?;
+
+    if bytes.len() as u64 > max_size {
+        anyhow::bail!("larger than media.max_size");
+    }
+
+    Ok(bytes.to_vec())
+}
+
+async fn convert_image(state: &State, media: &Media) -> anyhow::Result<image::Converted> {
+    let bytes = download(state, &media.url).await?;
+    let options = state.options.media.clone();
+
+    task::spawn_blocking(move || image::convert(&bytes, &options))
+        .await
+        .context("image conversion panicked")?
+}
@@ -0,0 +1,112 @@
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::imageops::{self, FilterType};
+use image::{AnimationDecoder, DynamicImage, Frames, ImageFormat, RgbaImage};
+use std::io::Cursor;
+
+/// Frames decoded at most, later frames of longer animations are never sampled.
+const MAX_FRAMES_DECODED: usize = 1000;
+
+/// Frames sampled from an animation.
+pub struct Sampled {
+    pub frames: Vec<RgbaImage>,
+    /// Number of frames in the animation.
+    pub total: usize,
+}
+
+/// The frames of an animated GIF, WebP or PNG, `None` for any other image.
+fn decode(bytes: &[u8]) -> anyhow::Result<Option<Frames<'_>>> {
+    let cursor = Cursor::new(bytes);
+
+    let frames = match image::guess_format(bytes)? {
+        ImageFormat::Gif => GifDecoder::new(cursor)?.into_frames(),
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(cursor)?;
+
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+
+            decoder.into_frames()
+        }
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(cursor)?;
+
+            if !decoder.is_apng()? {
+                return Ok(None);
+            }
+
+            decoder.apng()?.into_frames()
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(frames))
+}
+
+/// Sample up to `count` frames spread evenly over an animation, `None` if it is not animated.
+///
+/// The animation is decoded twice rather than holding every frame in memory at once.
+pub fn sample(bytes: &[u8], count: usize) -> anyhow::Result<Option<Sampled>> {
+    let Some(frames) = decode(bytes)? else {
+        return Ok(None);
+    };
+
+    let total = frames.take(MAX_FRAMES_DECODED).count();
+
+    if total <= 1 || count == 0 {
+        return Ok(None);
+    }
+
+    let count = count.min(total);
+
+    // the middle of each of `count` equal segments
+    let indices = (0..count)
+        .map(|index| (2 * index + 1) * total / (2 * count))
+        .collect::<Vec<_>>();
+
+    let frames = decode(bytes)?
+        .expect("animation is still animated")
+        .take(total)
+        .enumerate()
+        .filter(|(index, _frame)| indices.contains(index))
+        .map(|(_index, frame)| Ok(frame?.into_buffer()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(Some(Sampled { frames, total }))
+}
+
+/// Tile `frames` in a grid that fits within `max_dimension`.
+pub fn contact_sheet(frames: &[RgbaImage], max_dimension: u32) -> RgbaImage {
+    let columns = (frames.len() as f64).sqrt().ceil() as u32;
+    let rows = (frames.len() as u32).div_ceil(columns);
+
+    let frames = frames
+        .iter()
+        .map(|frame| {
+            let width = (max_dimension / columns).min(frame.width()).max(1);
+            let height = (max_dimension / rows).min(frame.height()).max(1);
+
+            DynamicImage::ImageRgba8(frame.clone())
+                .resize(width, height, FilterType::Triangle)
+                .to_rgba8()
+        })
+        .collect::<Vec<_>>();
+
+    // frames of an animation share their size
+    let cell_width = frames.iter().map(RgbaImage::width).max().unwrap_or(1);
+    let cell_height = frames.iter().map(RgbaImage::height).max().unwrap_or(1);
+
+    let mut sheet = RgbaImage::new(cell_width * columns, cell_height * rows);
+
+    for (index, frame) in frames.iter().enumerate() {
+        let index = index as u32;
+        let x = (index % columns) * cell_width;
+        let y = (index / columns) * cell_height;
+
+        imageops::replace(&mut sheet, frame, x.into(), y.into());
+    }
+
+    sheet
+}
@@ -0,0 +1,235 @@
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Blob;
+use audiopus::coder::{Decoder, Encoder};
+use audiopus::packet::Packet;
+use audiopus::{Application, Channels, MutSignals, SampleRate};
+use ogg::reading::PacketReader;
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use std::io::Cursor;
+use std::time::Duration;
+
+mod wav;
+
+/// Sample rate of audio sent to the model.
+const SAMPLE_RATE: usize = 16_000;
+
+/// Samples in the longest possible Opus frame, 120 ms.
+const MAX_FRAME_SAMPLES: usize = SAMPLE_RATE * 120 / 1000;
+
+/// Opus packets written per second, each holding 20 ms.
+const FRAMES_PER_SECOND: u32 = 50;
+
+/// Samples at 48 kHz a decoder drops from the start, the encoder's usual lookahead.
+const PRE_SKIP: u16 = 312;
+
+/// Bitstream serial number, any value works for a single stream.
+const SERIAL: u32 = 1;
+
+/// Audio ready to be sent to the model.
+pub struct Converted {
+    pub blob: Blob,
+    pub duration: Duration,
+    /// Whether the audio was longer than the maximum duration.
+    pub truncated: bool,
+}
+
+/// Decode Ogg Opus or WAV into 16 kHz, 16 bit mono PCM, keeping at most `max_duration` of it.
+pub fn convert(bytes: &[u8], max_duration: Duration) -> anyhow::Result<Converted> {
+    let max_samples = (max_duration.as_secs_f64() * SAMPLE_RATE as f64) as usize;
+
+    let (samples, truncated) = if bytes.starts_with(b"OggS") {
+        decode_opus(bytes, max_samples)?
+    } else if bytes.starts_with(b"RIFF") {
+        wav::decode(bytes, SAMPLE_RATE, max_samples)?
+    } else {
+        anyhow::bail!("unsupported audio format, only Ogg Opus and WAV can be played");
+    };
+
+    if samples.is_empty() {
+        anyhow::bail!("no audio");
+    }
+
+    Ok(Converted {
+        duration: Duration::from_secs_f64(samples.len() as f64 / SAMPLE_RATE as f64),
+        blob: Blob {
+            mime_type: format!("audio/pcm;rate={SAMPLE_RATE}"),
+            data: samples
+                .iter()
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect(),
+        },
+        truncated,
+    })
+}
+
+/// Decode Ogg Opus into 16 kHz mono samples, keeping at most `max_samples` of them.
+fn decode_opus(bytes: &[u8], max_samples: usize) -> anyhow::Result<(Vec<i16>, bool)> {
+    let mut reader = PacketReader::new(Cursor::new(bytes));
+    let mut decoder = Decoder::new(SampleRate::Hz16000, Channels::Mono)?;
+
+    let mut samples = Vec::new();
+    let mut frame = [0_i16; MAX_FRAME_SAMPLES];
+
+    let mut header = false;
+    let mut pre_skip = 0;
+    let mut skip = 0;
+    let mut truncated = false;
+
+    while let Some(packet) = reader.read_packet()? {
+        if packet.data.starts_with(b"OpusHead") {
+            // samples to discard from the start, counted at 48 kHz
+            pre_skip = packet
+                .data
+                .get(10..12)
+                .map_or(0, |pre_skip| u16::from_le_bytes([pre_skip[0], pre_skip[1]]));
+
+            header = true;
+            skip = usize::from(pre_skip) * SAMPLE_RATE / 48_000;
+
+            continue;
+        }
+
+        if !header {
+            anyhow::bail!("not an Opus stream");
+        }
+
+        if packet.data.starts_with(b"OpusTags") {
+            continue;
+        }
+
+        let decoded = decoder.decode(
+            Some(Packet::try_from(packet.data.as_slice())?),
+            MutSignals::try_from(&mut frame[..])?,
+            false,
+        )?;
+
+        let skipped = skip.min(decoded);
+        skip -= skipped;
+
+        samples.extend_from_slice(&frame[skipped..decoded]);
+
+        // the last frame is padded, the final granule position says where the audio ends
+        if packet.last_in_stream() {
+            let end = packet.absgp_page().saturating_sub(u64::from(pre_skip)) as usize;
+
+            samples.truncate(end * SAMPLE_RATE / 48_000);
+        }
+
+        if samples.len() > max_samples {
+            samples.truncate(max_samples);
+            truncated = true;
+
+            break;
+        }
+    }
+
+    Ok((samples, truncated))
+}
+
+/// The sample rate of an `audio/pcm;rate=…` mime type.
+pub fn sample_rate(mime_type: &str) -> Option<u32> {
+    mime_type
+        .split(';')
+        .find_map(|parameter| parameter.trim().strip_prefix("rate="))
+        .and_then(|rate| rate.parse().ok())
+}
+
+/// 16 bit little endian PCM as samples.
+pub fn samples(pcm: &[u8]) -> Vec<i16> {
+    pcm.chunks_exact(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect()
+}
+
+/// Encode 16 bit mono PCM as Ogg Opus.
+pub fn encode_ogg(samples: &[i16], sample_rate: u32) -> anyhow::Result<Vec<u8>> {
+    if samples.is_empty() {
+        anyhow::bail!("no audio");
+    }
+
+    let encoder = Encoder::new(
+        SampleRate::try_from(sample_rate as i32)?,
+        Channels::Mono,
+        Application::Voip,
+    )?;
+
+    let mut writer = PacketWriter::new(Vec::new());
+
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1);
+    head.push(1);
+    head.extend_from_slice(&PRE_SKIP.to_le_bytes());
+    head.extend_from_slice(&sample_rate.to_le_bytes());
+    head.extend_from_slice(&0_i16.to_le_bytes());
+    head.push(0);
+
+    writer.write_packet(head, SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+    let vendor = b"ari";
+    let mut tags = Vec::with_capacity(16 + vendor.len());
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0_u32.to_le_bytes());
+
+    writer.write_packet(tags, SERIAL, PacketWriteEndInfo::EndPage, 0)?;
+
+    let frame_size = (sample_rate / FRAMES_PER_SECOND) as usize;
+    // granule positions always count samples at 48 kHz
+    let scale = u64::from(48_000 / sample_rate);
+    let frames = samples.chunks(frame_size);
+    let count = frames.len();
+    let mut position = u64::from(PRE_SKIP);
+    let mut packet = [0; 4000];
+
+    for (index, frame) in frames.enumerate() {
+        // the last frame is padded with silence, which the final granule position cuts off
+        let length = if frame.len() == frame_size {
+            encoder.encode(frame, &mut packet)?
+        } else {
+            let mut padded = frame.to_vec();
+            padded.resize(frame_size, 0);
+
+            encoder.encode(&padded, &mut packet)?
+        };
+
+        position += frame.len() as u64 * scale;
+
+        let end = if index + 1 == count {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+
+        writer.write_packet(packet[..length].to_vec(), SERIAL, end, position)?;
+    }
+
+    Ok(writer.into_inner())
+}
+
+/// Wrap 16 bit mono PCM in a WAV header.
+pub fn encode_wav(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+    let data_length = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + samples.len() * 2);
+
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_length).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16_u32.to_le_bytes());
+    // pcm, mono
+    wav.extend_from_slice(&1_u16.to_le_bytes());
+    wav.extend_from_slice(&1_u16.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    // block align and bits per sample
+    wav.extend_from_slice(&2_u16.to_le_bytes());
+    wav.extend_from_slice(&16_u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_length.to_le_bytes());
+
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    wav
+}
@@ -0,0 +1,206 @@
+//! Reading uncompressed WAV files.
+
+use anyhow::Context;
+
+/// How the samples of a WAV file are stored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Encoding {
+    Integer,
+    Float,
+}
+
+/// The `fmt ` chunk of a WAV file.
+#[derive(Clone, Copy, Debug)]
+struct Format {
+    encoding: Encoding,
+    channels: usize,
+    sample_rate: usize,
+    /// Bytes per sample of a single channel.
+    width: usize,
+}
+
+impl Format {
+    fn parse(chunk: &[u8]) -> anyhow::Result<Self> {
+        if chunk.len() < 16 {
+            anyhow::bail!("truncated WAV format");
+        }
+
+        let tag = u16::from_le_bytes([chunk[0], chunk[1]]);
+        let channels = u16::from_le_bytes([chunk[2], chunk[3]]);
+        let sample_rate = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        let bits = u16::from_le_bytes([chunk[14], chunk[15]]);
+
+        // WAVE_FORMAT_EXTENSIBLE keeps the actual format at the start of its subformat
+        let tag = if tag == 0xfffe {
+            chunk
+                .get(24..26)
+                .map(|tag| u16::from_le_bytes([tag[0], tag[1]]))
+                .context("truncated WAV format")?
+        } else {
+            tag
+        };
+
+        let encoding = match (tag, bits) {
+            (1, 8 | 16 | 24 | 32) => Encoding::Integer,
+            (3, 32 | 64) => Encoding::Float,
+            _ => anyhow::bail!("unsupported WAV encoding {tag} with {bits} bits"),
+        };
+
+        if channels == 0 || sample_rate == 0 {
+            anyhow::bail!("WAV without channels or samples");
+        }
+
+        Ok(Self {
+            encoding,
+            channels: usize::from(channels),
+            sample_rate: sample_rate as usize,
+            width: usize::from(bits / 8),
+        })
+    }
+
+    /// A single sample, between -1 and 1.
+    fn sample(self, bytes: &[u8]) -> f32 {
+        match (self.encoding, bytes) {
+            (Encoding::Integer, [a]) => (f32::from(*a) - 128.0) / 128.0,
+            (Encoding::Integer, [a, b]) => f32::from(i16::from_le_bytes([*a, *b])) / 32_768.0,
+            (Encoding::Integer, [a, b, c]) => {
+                (i32::from_le_bytes([0, *a, *b, *c]) >> 8) as f32 / 8_388_608.0
+            }
+            (Encoding::Integer, [a, b, c, d]) => {
+                i32::from_le_bytes([*a, *b, *c, *d]) as f32 / 2_147_483_648.0
+            }
+            (Encoding::Float, [a, b, c, d]) => f32::from_le_bytes([*a, *b, *c, *d]),
+            (Encoding::Float, bytes) => {
+                f64::from_le_bytes(bytes.try_into().unwrap_or_default()) as f32
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Decode WAV into mono samples at `sample_rate`, keeping at most `max_samples` of them.
+///
+/// Channels are mixed down and the audio is resampled linearly, which is plenty for speech.
+/// Returns whether any samples were cut off.
+pub fn decode(
+    bytes: &[u8],
+    sample_rate: usize,
+    max_samples: usize,
+) -> anyhow::Result<(Vec<i16>, bool)> {
+    if bytes.get(..4) != Some(b"RIFF") || bytes.get(8..12) != Some(b"WAVE") {
+        anyhow::bail!("not a WAV file");
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut rest = &bytes[12..];
+
+    while rest.len() >= 8 {
+        let length = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let body = &rest[8..];
+        // recordings that were cut short claim more data than there is
+        let chunk = &body[..length.min(body.len())];
+
+        match &rest[..4] {
+            b"fmt " => format = Some(Format::parse(chunk)?),
+            b"data" => data = Some(chunk),
+            _ => {}
+        }
+
+        // chunks are padded to an even length
+        rest = body
+            .get(length.saturating_add(length % 2)..)
+            .unwrap_or_default();
+    }
+
+    let format = format.context("WAV without a format")?;
+    let data = data.context("WAV without data")?;
+
+    let mono = data
+        .chunks_exact(format.channels * format.width)
+        .map(|frame| {
+            frame
+                .chunks_exact(format.width)
+                .map(|sample| format.sample(sample))
+                .sum::<f32>()
+                / format.channels as f32
+        })
+        .collect::<Vec<_>>();
+
+    let length = mono.len() * sample_rate / format.sample_rate;
+    let truncated = length > max_samples;
+    let step = format.sample_rate as f64 / sample_rate as f64;
+
+    let samples = (0..length.min(max_samples))
+        .map(|index| {
+            let position = index as f64 * step;
+            let before = position as usize;
+            let fraction = (position - before as f64) as f32;
+
+            let before_sample = mono[before];
+            let after_sample = mono.get(before + 1).copied().unwrap_or(before_sample);
+            let sample = before_sample + (after_sample - before_sample) * fraction;
+
+            (sample * 32_768.0).clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16
+        })
+        .collect();
+
+    Ok((samples, truncated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+    use crate::media::audio::encode_wav;
+
+    #[test]
+    fn same_rate() {
+        let samples = [0, 1000, -1000, i16::MAX, i16::MIN + 1];
+        let (decoded, truncated) = decode(&encode_wav(&samples, 16_000), 16_000, 100).unwrap();
+
+        assert_eq!(decoded, samples);
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn resample() {
+        let samples = (0..24_000)
+            .map(|index| (index % 100) as i16)
+            .collect::<Vec<_>>();
+        let (decoded, truncated) = decode(&encode_wav(&samples, 24_000), 16_000, 16_000).unwrap();
+
+        assert_eq!(decoded.len(), 16_000);
+        assert!(!truncated);
+        assert_eq!(decoded[3], 4);
+
+        let (decoded, truncated) = decode(&encode_wav(&samples, 24_000), 16_000, 8_000).unwrap();
+
+        assert_eq!(decoded.len(), 8_000);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn stereo_float() {
+        let mut wav = encode_wav(&[], 16_000);
+        // float samples in two channels
+        wav[20..22].copy_from_slice(&3_u16.to_le_bytes());
+        wav[22..24].copy_from_slice(&2_u16.to_le_bytes());
+        wav[34..36].copy_from_slice(&32_u16.to_le_bytes());
+
+        for sample in [0.5_f32, -0.5, 1.0, 0.0] {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        wav[40..44].copy_from_slice(&16_u32.to_le_bytes());
+
+        let (decoded, _truncated) = decode(&wav, 16_000, 100).unwrap();
+
+        assert_eq!(decoded, [0, 16_384]);
+    }
+
+    #[test]
+    fn rejects() {
+        assert!(decode(b"OggS", 16_000, 100).is_err());
+        assert!(decode(b"RIFF\0\0\0\0WAVE", 16_000, 100).is_err());
+    }
+}
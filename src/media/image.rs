@@ -0,0 +1,77 @@
+use super::MediaOptions;
+use super::animation::{self, Sampled};
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Blob;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+
+/// Quality of re-encoded JPEGs, high enough for text in screenshots to stay legible.
+const JPEG_QUALITY: u8 = 85;
+
+/// An image ready to be sent to the model.
+pub struct Converted {
+    pub blobs: Vec<Blob>,
+    /// Number of frames, if the blobs were sampled from an animation.
+    pub frames: Option<usize>,
+}
+
+/// Decode an image, downscale it to fit within the maximum dimension and re-encode it.
+///
+/// Animations are sampled into a contact sheet or into separate frames, see [`MediaOptions`].
+pub fn convert(bytes: &[u8], options: &MediaOptions) -> anyhow::Result<Converted> {
+    if let Some(Sampled { frames, total }) = animation::sample(bytes, options.max_frames)? {
+        let blobs = if options.contact_sheet {
+            let sheet = animation::contact_sheet(&frames, options.max_dimension);
+
+            vec![encode(&DynamicImage::ImageRgba8(sheet))?]
+        } else {
+            frames
+                .into_iter()
+                .map(|frame| encode(&downscale(frame.into(), options.max_dimension)))
+                .collect::<anyhow::Result<_>>()?
+        };
+
+        return Ok(Converted {
+            blobs,
+            frames: Some(total),
+        });
+    }
+
+    let image = image::load_from_memory(bytes)?;
+
+    Ok(Converted {
+        blobs: vec![encode(&downscale(image, options.max_dimension))?],
+        frames: None,
+    })
+}
+
+pub fn downscale(image: DynamicImage, max_dimension: u32) -> DynamicImage {
+    if image.width() <= max_dimension && image.height() <= max_dimension {
+        return image;
+    }
+
+    image.resize(max_dimension, max_dimension, FilterType::Lanczos3)
+}
+
+pub fn encode(image: &DynamicImage) -> anyhow::Result<Blob> {
+    let mut data = Vec::new();
+
+    if image.color().has_alpha() {
+        image.write_to(&mut Cursor::new(&mut data), ImageFormat::Png)?;
+
+        return Ok(Blob {
+            mime_type: String::from("image/png"),
+            data,
+        });
+    }
+
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY))?;
+
+    Ok(Blob {
+        mime_type: String::from("image/jpeg"),
+        data,
+    })
+}
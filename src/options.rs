@@ -0,0 +1,535 @@
+use crate::event::EventOptions;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::generation_config::Modality;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{
+    GenerationConfig, PrebuiltVoiceConfig, SpeechConfig, VoiceConfig, voice_config,
+};
+use crate::media::MediaOptions;
+use crate::session::Scope;
+use crate::trigger::TriggerOptions;
+use crate::voice::VoiceOptions;
+use anyhow::Context;
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::warn;
+use twilight_gateway::Intents;
+use twilight_model::channel::message::{AllowedMentions, MentionType};
+use twilight_model::gateway::presence::{ActivityType, Status};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{RoleMarker, UserMarker};
+
+/// Intents that have to be enabled for the application in the developer portal.
+const PRIVILEGED_INTENTS: Intents = Intents::GUILD_MEMBERS
+    .union(Intents::GUILD_PRESENCES)
+    .union(Intents::MESSAGE_CONTENT);
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Playing,
+    Streaming,
+    Listening,
+    Watching,
+    Custom,
+    Competing,
+}
+
+impl From<ActivityKind> for ActivityType {
+    fn from(kind: ActivityKind) -> Self {
+        match kind {
+            ActivityKind::Playing => ActivityType::Playing,
+            ActivityKind::Streaming => ActivityType::Streaming,
+            ActivityKind::Listening => ActivityType::Listening,
+            ActivityKind::Watching => ActivityType::Watching,
+            ActivityKind::Custom => ActivityType::Custom,
+            ActivityKind::Competing => ActivityType::Competing,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ActivityOptions {
+    pub kind: ActivityKind,
+    /// Leave empty to show no activity at all.
+    pub text: String,
+    /// Stream url, only used with the `streaming` kind.
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShardOptions {
+    /// Total number of shards across every process running ari.
+    pub total: u32,
+    /// The shards this process runs, defaults to all of them.
+    pub ids: Option<Vec<u32>>,
+}
+
+impl ShardOptions {
+    pub fn ids(&self) -> Vec<u32> {
+        self.ids
+            .clone()
+            .unwrap_or_else(|| (0..self.total).collect())
+    }
+}
+
+impl Default for ShardOptions {
+    fn default() -> Self {
+        Self {
+            total: 1,
+            ids: None,
+        }
+    }
+}
+
+/// How the model's own text output reaches Discord.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyMode {
+    /// Text output is discarded, the model only speaks through `discord_send_message`.
+    #[default]
+    Tools,
+    /// Text output is posted to the channel that started the turn once the turn completes.
+    Direct,
+    /// Like `direct`, but the message is posted right away and edited as text is generated.
+    Streaming,
+}
+
+/// Container the model's spoken replies are uploaded in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    /// Opus in an Ogg container, like Discord's own voice messages.
+    #[default]
+    Ogg,
+    /// Uncompressed, several times larger.
+    Wav,
+}
+
+/// Which users mentions in ari's messages ping.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserMentions {
+    #[default]
+    None,
+    All,
+    /// Only the authors of and users mentioned in the messages the model is responding to.
+    Triggering,
+}
+
+/// Who ari's messages may ping, `@everyone` and `@here` never do.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MentionOptions {
+    #[serde(default)]
+    pub users: UserMentions,
+    /// Roles that may be pinged, ids are quoted strings.
+    #[serde(default)]
+    pub roles: Vec<Id<RoleMarker>>,
+}
+
+impl MentionOptions {
+    /// The mentions allowed in a message responding to `users`.
+    pub fn allowed_mentions(&self, users: &[Id<UserMarker>]) -> AllowedMentions {
+        let mut allowed_mentions = AllowedMentions {
+            roles: self.roles.clone(),
+            ..Default::default()
+        };
+
+        match self.users {
+            UserMentions::None => {}
+            UserMentions::All => allowed_mentions.parse.push(MentionType::Users),
+            // discord rejects more than 100 ids
+            UserMentions::Triggering => {
+                allowed_mentions.users = users.iter().copied().take(100).collect()
+            }
+        }
+
+        allowed_mentions
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DiscordOptions {
+    /// Can also be provided with `ARI_DISCORD_TOKEN` or `ARI_DISCORD_TOKEN_FILE`.
+    #[serde(default)]
+    pub token: String,
+    /// Gateway intents by name, such as `GUILD_MESSAGES`.
+    #[serde(default = "default_intents", deserialize_with = "deserialize_intents")]
+    pub intents: Intents,
+    #[serde(default = "default_status")]
+    pub status: Status,
+    #[serde(default = "default_activity")]
+    pub activity: ActivityOptions,
+    #[serde(default)]
+    pub shards: ShardOptions,
+    #[serde(default)]
+    pub reply: ReplyMode,
+    #[serde(default)]
+    pub mentions: MentionOptions,
+    /// How spoken replies are uploaded with the `audio` response modality, without a transcript.
+    #[serde(default)]
+    pub audio_format: AudioFormat,
+}
+
+fn default_intents() -> Intents {
+    Intents::all().difference(Intents::GUILD_PRESENCES)
+}
+
+fn deserialize_intents<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Intents, D::Error> {
+    use serde::de::Error;
+
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .try_fold(Intents::empty(), |intents, name| {
+            Intents::from_name(name)
+                .map(|intent| intents | intent)
+                .ok_or_else(|| D::Error::custom(format!("unknown intent `{name}`")))
+        })
+}
+
+fn default_status() -> Status {
+    Status::Invisible
+}
+
+fn default_activity() -> ActivityOptions {
+    ActivityOptions {
+        kind: ActivityKind::Custom,
+        text: String::from("soup"),
+        url: None,
+    }
+}
+
+impl DiscordOptions {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.token.is_empty() {
+            anyhow::bail!(
+                "discord.token is missing, set it in the options file, ARI_DISCORD_TOKEN or ARI_DISCORD_TOKEN_FILE"
+            );
+        }
+
+        let privileged = self.intents.intersection(PRIVILEGED_INTENTS);
+
+        for (name, _intent) in privileged.iter_names() {
+            warn!(
+                "privileged intent {name} requested, it must be enabled for the application in the developer portal"
+            );
+        }
+
+        if !self.intents.contains(Intents::MESSAGE_CONTENT) {
+            warn!("MESSAGE_CONTENT intent not requested, message content will be empty");
+        }
+
+        if self.shards.total == 0 {
+            anyhow::bail!("discord.shards.total must be at least 1");
+        }
+
+        if let Some(id) = self
+            .shards
+            .ids()
+            .into_iter()
+            .find(|id| *id >= self.shards.total)
+        {
+            anyhow::bail!(
+                "discord.shards.ids contains {id}, which is not less than discord.shards.total"
+            );
+        }
+
+        if self.shards.ids.as_ref().is_some_and(Vec::is_empty) {
+            anyhow::bail!("discord.shards.ids must not be empty");
+        }
+
+        if self.mentions.roles.len() > 100 {
+            anyhow::bail!("discord.mentions.roles must contain at most 100 roles");
+        }
+
+        Ok(())
+    }
+}
+
+/// What the model responds with.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseModality {
+    Text,
+    Image,
+    Audio,
+}
+
+impl From<ResponseModality> for Modality {
+    fn from(modality: ResponseModality) -> Self {
+        match modality {
+            ResponseModality::Text => Modality::Text,
+            ResponseModality::Image => Modality::Image,
+            ResponseModality::Audio => Modality::Audio,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GeminiOptions {
+    /// Can also be provided with `ARI_GEMINI_API_KEY` or `ARI_GEMINI_API_KEY_FILE`.
+    #[serde(default)]
+    pub api_key: String,
+    pub system_instructions: String,
+    #[serde(default)]
+    pub scope: Scope,
+    /// Seconds without activity after which a session is closed.
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout: u64,
+    /// Model name, without the `models/` prefix.
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[serde(default = "default_response_modalities")]
+    pub response_modalities: Vec<ResponseModality>,
+    /// Prebuilt voice the model speaks with in the `audio` modality, such as `Puck`.
+    pub voice: Option<String>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<i32>,
+    pub max_output_tokens: Option<i32>,
+    pub candidate_count: Option<i32>,
+    #[serde(default)]
+    pub stop_sequences: Vec<String>,
+}
+
+fn default_idle_timeout() -> u64 {
+    15 * 60
+}
+
+fn default_model() -> String {
+    String::from("gemini-2.0-flash-live-001")
+}
+
+fn default_response_modalities() -> Vec<ResponseModality> {
+    vec![ResponseModality::Text]
+}
+
+impl GeminiOptions {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.api_key.is_empty() {
+            anyhow::bail!(
+                "gemini.api_key is missing, set it in the options file, ARI_GEMINI_API_KEY or ARI_GEMINI_API_KEY_FILE"
+            );
+        }
+
+        if self.model.trim().is_empty() {
+            anyhow::bail!("gemini.model must not be empty");
+        }
+
+        if self.model.starts_with("models/") {
+            anyhow::bail!("gemini.model must not include the `models/` prefix");
+        }
+
+        // the live api only ever responds in a single modality
+        if self.response_modalities.len() != 1 {
+            anyhow::bail!("gemini.response_modalities must contain exactly one modality");
+        }
+
+        if self
+            .voice
+            .as_ref()
+            .is_some_and(|voice| voice.trim().is_empty())
+        {
+            anyhow::bail!("gemini.voice must not be empty");
+        }
+
+        if let Some(temperature) = self
+            .temperature
+            .filter(|temperature| !(0.0..=2.0).contains(temperature))
+        {
+            anyhow::bail!("gemini.temperature must be between 0 and 2, got {temperature}");
+        }
+
+        if let Some(top_p) = self.top_p.filter(|top_p| !(0.0..=1.0).contains(top_p)) {
+            anyhow::bail!("gemini.top_p must be between 0 and 1, got {top_p}");
+        }
+
+        if let Some(top_k) = self.top_k.filter(|top_k| *top_k < 1) {
+            anyhow::bail!("gemini.top_k must be at least 1, got {top_k}");
+        }
+
+        if let Some(max_output_tokens) = self
+            .max_output_tokens
+            .filter(|max_output_tokens| *max_output_tokens < 1)
+        {
+            anyhow::bail!("gemini.max_output_tokens must be at least 1, got {max_output_tokens}");
+        }
+
+        if let Some(candidate_count) = self
+            .candidate_count
+            .filter(|candidate_count| *candidate_count != 1)
+        {
+            anyhow::bail!("gemini.candidate_count must be 1, got {candidate_count}");
+        }
+
+        if self.stop_sequences.len() > 5 {
+            anyhow::bail!("gemini.stop_sequences must contain at most 5 sequences");
+        }
+
+        Ok(())
+    }
+
+    pub fn generation_config(&self) -> GenerationConfig {
+        let mut generation_config = GenerationConfig {
+            candidate_count: self.candidate_count,
+            stop_sequences: self.stop_sequences.clone(),
+            max_output_tokens: self.max_output_tokens,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            top_k: self.top_k,
+            ..Default::default()
+        };
+
+        for modality in &self.response_modalities {
+            generation_config.push_response_modalities((*modality).into());
+        }
+
+        generation_config.speech_config = self.voice.clone().map(|voice_name| SpeechConfig {
+            voice_config: Some(VoiceConfig {
+                voice_config: Some(voice_config::VoiceConfig::PrebuiltVoiceConfig(
+                    PrebuiltVoiceConfig {
+                        voice_name: Some(voice_name),
+                    },
+                )),
+            }),
+        });
+
+        generation_config
+    }
+}
+
+/// Whose messages a tool may act on besides ari's own, based on the users who triggered the turn.
+///
+/// When several users' messages are answered in one turn, all of them must be trusted.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TargetPolicy {
+    /// Users with Manage Messages in the channel.
+    #[serde(default)]
+    pub manage_messages: bool,
+    /// Users with any of these roles, ids are quoted strings.
+    #[serde(default)]
+    pub roles: Vec<Id<RoleMarker>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ToolOptions {
+    /// Also require every user who triggered a turn to have the permissions a tool needs, so the
+    /// model cannot be talked into doing something on their behalf they could not do themselves.
+    #[serde(default)]
+    pub check_user_permissions: bool,
+    /// Who may have `discord_delete_message` delete messages by others.
+    #[serde(default = "default_delete_message")]
+    pub delete_message: TargetPolicy,
+}
+
+fn default_delete_message() -> TargetPolicy {
+    TargetPolicy {
+        manage_messages: true,
+        roles: Vec::new(),
+    }
+}
+
+impl Default for ToolOptions {
+    fn default() -> Self {
+        Self {
+            check_user_permissions: false,
+            delete_message: default_delete_message(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Options {
+    pub discord: DiscordOptions,
+    pub gemini: GeminiOptions,
+    #[serde(default)]
+    pub tools: ToolOptions,
+    #[serde(default)]
+    pub trigger: TriggerOptions,
+    #[serde(default)]
+    pub events: EventOptions,
+    #[serde(default)]
+    pub media: MediaOptions,
+    #[serde(default)]
+    pub voice: VoiceOptions,
+}
+
+impl Options {
+    /// Read the options file and apply overrides from the environment.
+    ///
+    /// The file is `--config <path>`, `ARI_CONFIG`, or `options.toml` in the working directory.
+    pub async fn load() -> anyhow::Result<Self> {
+        let path = config_path()?;
+
+        let text = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("failed to read options from {}", path.display()))?;
+
+        let mut options: Options = toml::from_str(&text)
+            .with_context(|| format!("failed to parse options from {}", path.display()))?;
+
+        if let Some(token) = secret("ARI_DISCORD_TOKEN").await? {
+            options.discord.token = token;
+        }
+
+        if let Some(api_key) = secret("ARI_GEMINI_API_KEY").await? {
+            options.gemini.api_key = api_key;
+        }
+
+        options.validate()?;
+
+        Ok(options)
+    }
+
+    /// Check for values the APIs would reject, so mistakes surface at startup.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.discord.validate()?;
+        self.gemini.validate()?;
+        self.trigger.validate()?;
+        self.media.validate()?;
+
+        if self.voice.enabled && !self.discord.intents.contains(Intents::GUILD_VOICE_STATES) {
+            anyhow::bail!("voice.enabled needs the GUILD_VOICE_STATES intent");
+        }
+
+        Ok(())
+    }
+}
+
+/// Where to read options from, `--config` takes precedence over `ARI_CONFIG`.
+fn config_path() -> anyhow::Result<PathBuf> {
+    let mut args = env::args_os().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--config" || arg == "-c" {
+            let path = args.next().context("--config requires a path")?;
+
+            return Ok(PathBuf::from(path));
+        }
+
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--config=")) {
+            return Ok(PathBuf::from(path));
+        }
+    }
+
+    Ok(env::var_os("ARI_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("options.toml")))
+}
+
+/// Read a secret from `name`, or from the file named by `{name}_FILE`.
+async fn secret(name: &str) -> anyhow::Result<Option<String>> {
+    if let Some(value) = env::var(name).ok().filter(|value| !value.is_empty()) {
+        return Ok(Some(value));
+    }
+
+    let Some(path) = env::var_os(format!("{name}_FILE")).map(PathBuf::from) else {
+        return Ok(None);
+    };
+
+    let value = fs::read_to_string(&path)
+        .await
+        .with_context(|| format!("failed to read {name}_FILE from {}", path.display()))?;
+
+    Ok(Some(value.trim_end_matches(['\r', '\n']).to_string()))
+}
@@ -0,0 +1,171 @@
+//! Rendering Discord messages as text for the model.
+
+use crate::State;
+use regex::{Captures, Regex};
+use std::fmt::Write as _;
+use std::sync::LazyLock;
+use time::OffsetDateTime;
+use time::format_description::BorrowedFormatItem;
+use time::macros::format_description;
+use twilight_model::channel::Message;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, UserMarker};
+
+const TIME: &[BorrowedFormatItem<'_>] = format_description!(
+    "[hour]:[minute]:[second] UTC[offset_hour sign:mandatory]:[offset_minute] [weekday], [month], [day] [week_number], [year]"
+);
+
+/// Characters of a referenced message or embed description included.
+const EXCERPT_LENGTH: usize = 200;
+
+/// User, role and channel mentions, as well as custom emojis.
+static MENTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<(@!?|@&|#|a?:(\w+):)(\d+)>").expect("mention pattern is valid"));
+
+/// Render a new `message` as the text the model reads.
+pub fn message(state: &State, message: &Message) -> anyhow::Result<String> {
+    render(state, message, "A new message")
+}
+
+/// Render an edited `message`, which replaces what the model read before.
+pub fn edited_message(state: &State, message: &Message) -> anyhow::Result<String> {
+    render(state, message, "An edit of the message")
+}
+
+/// The current time, as shown alongside messages.
+///
+/// The local offset cannot be determined soundly once the process has other threads, in that case
+/// the time is in UTC.
+pub fn now() -> anyhow::Result<String> {
+    let now = OffsetDateTime::now_local().unwrap_or_else(|_error| OffsetDateTime::now_utc());
+
+    Ok(now.format(&TIME)?)
+}
+
+pub fn channel_name(state: &State, channel_id: Id<ChannelMarker>) -> String {
+    state
+        .cache
+        .channel(channel_id)
+        .and_then(|channel| channel.name.clone())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+fn render(state: &State, message: &Message, heading: &str) -> anyhow::Result<String> {
+    let channel_name = channel_name(state, message.channel_id);
+
+    let author = message
+        .member
+        .as_ref()
+        .and_then(|member| member.nick.clone())
+        .or_else(|| message.author.global_name.clone())
+        .unwrap_or_else(|| message.author.name.clone());
+
+    let mut text = format!(
+        "{heading} by {author} (@{username}, user_id={user_id}) in #{channel_name} {time}: channel_id={channel_id} message_id={message_id} content={content:?}",
+        username = message.author.name,
+        user_id = message.author.id,
+        time = now()?,
+        channel_id = message.channel_id,
+        message_id = message.id,
+        content = content(state, message.guild_id, &message.content),
+    );
+
+    if let Some(referenced) = &message.referenced_message {
+        let author = user_name(state, message.guild_id, referenced.author.id)
+            .unwrap_or_else(|| referenced.author.name.clone());
+
+        let _ = write!(
+            text,
+            "\nIn reply to {author} (message_id={message_id}): {excerpt:?}",
+            message_id = referenced.id,
+            excerpt = excerpt(&content(state, message.guild_id, &referenced.content)),
+        );
+    }
+
+    for attachment in &message.attachments {
+        let _ = write!(
+            text,
+            "\nAttachment: {filename} ({content_type}, {size} bytes)",
+            filename = attachment.filename,
+            content_type = attachment.content_type.as_deref().unwrap_or("unknown type"),
+            size = attachment.size,
+        );
+    }
+
+    for sticker in &message.sticker_items {
+        let _ = write!(text, "\nSticker: {}", sticker.name);
+    }
+
+    for embed in &message.embeds {
+        let _ = write!(text, "\nEmbed:");
+
+        if let Some(title) = &embed.title {
+            let _ = write!(text, " {title:?}");
+        }
+
+        if let Some(description) = &embed.description {
+            let _ = write!(text, " {:?}", excerpt(description));
+        }
+
+        if let Some(url) = &embed.url {
+            let _ = write!(text, " {url}");
+        }
+    }
+
+    Ok(text)
+}
+
+/// Replace mentions in `content` with the names they refer to.
+pub fn content(state: &State, guild_id: Option<Id<GuildMarker>>, content: &str) -> String {
+    MENTION
+        .replace_all(content, |captures: &Captures<'_>| {
+            let Some(id) = captures[3].parse::<u64>().ok().filter(|id| *id != 0) else {
+                return captures[0].to_string();
+            };
+
+            let name = match &captures[1] {
+                "@" | "@!" => {
+                    user_name(state, guild_id, Id::new(id)).map(|name| format!("@{name}"))
+                }
+                "@&" => state
+                    .cache
+                    .role(Id::new(id))
+                    .map(|role| format!("@{}", role.name)),
+                "#" => state
+                    .cache
+                    .channel(Id::new(id))
+                    .and_then(|channel| channel.name.clone())
+                    .map(|name| format!("#{name}")),
+                _ => captures.get(2).map(|name| format!(":{}:", name.as_str())),
+            };
+
+            name.unwrap_or_else(|| captures[0].to_string())
+        })
+        .into_owned()
+}
+
+/// The name `user_id` is shown as in `guild_id`, if they are cached.
+pub fn user_name(
+    state: &State,
+    guild_id: Option<Id<GuildMarker>>,
+    user_id: Id<UserMarker>,
+) -> Option<String> {
+    let nick = guild_id
+        .and_then(|guild_id| state.cache.member(guild_id, user_id))
+        .and_then(|member| member.nick().map(str::to_string));
+
+    nick.or_else(|| {
+        state.cache.user(user_id).map(|user| {
+            user.global_name
+                .clone()
+                .unwrap_or_else(|| user.name.clone())
+        })
+    })
+}
+
+fn excerpt(text: &str) -> String {
+    match text.char_indices().nth(EXCERPT_LENGTH) {
+        Some((index, _char)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,339 @@
+//! Posting text to Discord.
+//!
+//! Messages are limited to 2000 characters, so everything sent or edited here is split into
+//! several messages, or uploaded as a file once it would take too many of them.
+
+use self::split::split;
+use crate::State;
+use std::time::Duration;
+use tokio::time::Instant;
+use twilight_model::channel::message::AllowedMentions;
+use twilight_model::http::attachment::Attachment;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, MessageMarker};
+
+mod split;
+
+/// Maximum number of characters in a message.
+pub const MAX_LENGTH: usize = 2000;
+
+/// Content needing more messages than this is uploaded as a file instead.
+const MAX_MESSAGES: usize = 4;
+
+/// Minimum time between edits of a streaming reply, Discord allows 5 edits per 5 seconds.
+const EDIT_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Appended to a streaming reply while the model is still generating.
+const IN_PROGRESS: &str = " …";
+
+/// Appended to a streaming reply when the model was interrupted.
+const CUT_OFF: &str = " — *(cut off)*";
+
+/// How content is posted.
+enum Outgoing {
+    Messages(Vec<String>),
+    File(Attachment),
+}
+
+impl Outgoing {
+    fn new(content: &str) -> anyhow::Result<Self> {
+        let messages = split(content, MAX_LENGTH);
+
+        if messages.is_empty() {
+            anyhow::bail!("message content must not be empty");
+        }
+
+        if messages.len() <= MAX_MESSAGES {
+            return Ok(Self::Messages(messages));
+        }
+
+        // code blocks or headings make the file worth rendering as markdown
+        let markdown = content.contains("```") || content.lines().any(|line| line.starts_with('#'));
+
+        let filename = if markdown {
+            "message.md"
+        } else {
+            "message.txt"
+        };
+
+        Ok(Self::File(Attachment::from_bytes(
+            filename.to_string(),
+            content.as_bytes().to_vec(),
+            0,
+        )))
+    }
+}
+
+/// Note posted along with content uploaded as a file.
+fn file_note(attachment: &Attachment) -> String {
+    format!("*(too long for a message, see {})*", attachment.filename)
+}
+
+/// Post `content` in `channel_id`, returning the messages it was posted as.
+pub async fn send(
+    state: &State,
+    channel_id: Id<ChannelMarker>,
+    content: &str,
+    allowed_mentions: &AllowedMentions,
+) -> anyhow::Result<Vec<Id<MessageMarker>>> {
+    match Outgoing::new(content)? {
+        Outgoing::Messages(messages) => {
+            let mut message_ids = Vec::with_capacity(messages.len());
+
+            for message in &messages {
+                let message = state
+                    .rest
+                    .create_message(channel_id)
+                    .content(message)
+                    .allowed_mentions(Some(allowed_mentions))
+                    .await?
+                    .model()
+                    .await?;
+
+                message_ids.push(message.id);
+            }
+
+            Ok(message_ids)
+        }
+        Outgoing::File(attachment) => {
+            let message = state
+                .rest
+                .create_message(channel_id)
+                .content(&file_note(&attachment))
+                .allowed_mentions(Some(allowed_mentions))
+                .attachments(&[attachment])
+                .await?
+                .model()
+                .await?;
+
+            Ok(vec![message.id])
+        }
+    }
+}
+
+/// Replace the content of `message_id`, returning any messages posted for content that did not
+/// fit in it.
+pub async fn edit(
+    state: &State,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+    content: &str,
+    allowed_mentions: &AllowedMentions,
+) -> anyhow::Result<Vec<Id<MessageMarker>>> {
+    match Outgoing::new(content)? {
+        Outgoing::Messages(messages) => {
+            let (first, rest) = messages.split_first().expect("messages are not empty");
+
+            state
+                .rest
+                .update_message(channel_id, message_id)
+                .content(Some(first))
+                .allowed_mentions(Some(allowed_mentions))
+                .await?;
+
+            let mut message_ids = Vec::with_capacity(rest.len());
+
+            for message in rest {
+                let message = state
+                    .rest
+                    .create_message(channel_id)
+                    .content(message)
+                    .allowed_mentions(Some(allowed_mentions))
+                    .await?
+                    .model()
+                    .await?;
+
+                message_ids.push(message.id);
+            }
+
+            Ok(message_ids)
+        }
+        Outgoing::File(attachment) => {
+            state
+                .rest
+                .update_message(channel_id, message_id)
+                .content(Some(&file_note(&attachment)))
+                .allowed_mentions(Some(allowed_mentions))
+                .attachments(&[attachment])
+                .await?;
+
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Upload `audio` in `channel_id` as a message of its own.
+pub async fn send_audio(
+    state: &State,
+    channel_id: Id<ChannelMarker>,
+    audio: Attachment,
+) -> anyhow::Result<Id<MessageMarker>> {
+    let message = state
+        .rest
+        .create_message(channel_id)
+        .attachments(&[audio])
+        .await?
+        .model()
+        .await?;
+
+    Ok(message.id)
+}
+
+/// A reply that is edited as the model generates it.
+///
+/// Text beyond the length limit continues in further messages, which are edited individually.
+pub struct StreamingReply {
+    channel_id: Id<ChannelMarker>,
+    /// Messages posted so far along with their current content.
+    messages: Vec<(Id<MessageMarker>, String)>,
+    text: String,
+    allowed_mentions: AllowedMentions,
+    /// When the messages were last sent or edited.
+    last_edit: Option<Instant>,
+    /// Whether `text` has changed since the last edit.
+    dirty: bool,
+}
+
+impl StreamingReply {
+    pub fn new(channel_id: Id<ChannelMarker>, allowed_mentions: AllowedMentions) -> Self {
+        Self {
+            channel_id,
+            messages: Vec::new(),
+            text: String::new(),
+            allowed_mentions,
+            last_edit: None,
+            dirty: false,
+        }
+    }
+
+    pub fn channel_id(&self) -> Id<ChannelMarker> {
+        self.channel_id
+    }
+
+    /// When pending text should be flushed, if there is any.
+    pub fn deadline(&self) -> Option<Instant> {
+        if !self.dirty {
+            return None;
+        }
+
+        Some(match self.last_edit {
+            Some(last_edit) => last_edit + EDIT_INTERVAL,
+            None => Instant::now(),
+        })
+    }
+
+    /// Append generated text, posting the message if it has not been posted yet.
+    pub async fn push(&mut self, state: &State, text: &str) -> anyhow::Result<()> {
+        self.text.push_str(text);
+        self.dirty = true;
+
+        if self
+            .deadline()
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            self.flush(state).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Edit the message with the text generated so far.
+    pub async fn flush(&mut self, state: &State) -> anyhow::Result<()> {
+        if self.dirty {
+            self.update(state, IN_PROGRESS).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the final text, marking it as cut off if the model was interrupted.
+    ///
+    /// A reply that grew too long for [`send`] to post as messages is replaced by a file.
+    pub async fn finish(mut self, state: &State, interrupted: bool) -> anyhow::Result<()> {
+        if self.text.trim().is_empty() && self.messages.is_empty() {
+            return Ok(());
+        }
+
+        let suffix = if interrupted { CUT_OFF } else { "" };
+
+        if split(&self.text, MAX_LENGTH).len() <= MAX_MESSAGES {
+            return self.update(state, suffix).await;
+        }
+
+        let Some(((first, _content), rest)) = self.messages.split_first() else {
+            send(state, self.channel_id, &self.text, &self.allowed_mentions).await?;
+
+            return Ok(());
+        };
+
+        edit(
+            state,
+            self.channel_id,
+            *first,
+            &self.text,
+            &self.allowed_mentions,
+        )
+        .await?;
+
+        for (message_id, _content) in rest {
+            state
+                .rest
+                .delete_message(self.channel_id, *message_id)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn update(&mut self, state: &State, suffix: &str) -> anyhow::Result<()> {
+        let limit = MAX_LENGTH - suffix.chars().count();
+        let mut pieces = split(&self.text, limit);
+
+        if let Some(last) = pieces.last_mut() {
+            last.push_str(suffix);
+        }
+
+        let count = pieces.len();
+
+        for (index, piece) in pieces.into_iter().enumerate() {
+            match self.messages.get_mut(index) {
+                Some((_message_id, content)) if *content == piece => {}
+                Some((message_id, content)) => {
+                    state
+                        .rest
+                        .update_message(self.channel_id, *message_id)
+                        .content(Some(&piece))
+                        .allowed_mentions(Some(&self.allowed_mentions))
+                        .await?;
+
+                    *content = piece;
+                }
+                None => {
+                    let message = state
+                        .rest
+                        .create_message(self.channel_id)
+                        .content(&piece)
+                        .allowed_mentions(Some(&self.allowed_mentions))
+                        .await?
+                        .model()
+                        .await?;
+
+                    self.messages.push((message.id, piece));
+                }
+            }
+        }
+
+        // the text can split into fewer pieces than before, once the " …" marker is gone
+        for (message_id, _content) in self.messages.split_off(count.min(self.messages.len())) {
+            state
+                .rest
+                .delete_message(self.channel_id, message_id)
+                .await?;
+        }
+
+        self.last_edit = Some(Instant::now());
+        self.dirty = false;
+
+        Ok(())
+    }
+}
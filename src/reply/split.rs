@@ -0,0 +1,231 @@
+//! Splitting text into pieces that fit in a message without breaking its markdown.
+
+/// Closes a code block left open at the end of a piece.
+const CLOSE_FENCE: &str = "\n```";
+
+/// Split `content` into pieces of at most `limit` characters.
+///
+/// Pieces end at paragraph breaks, then line breaks, then whitespace, and only mid-word when there
+/// is no other choice. Code blocks spanning pieces are closed and reopened with the same info
+/// string, so every piece renders on its own.
+pub fn split(content: &str, limit: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut rest = content.trim();
+    // the line that opened the code block `rest` starts in, such as "```rust"
+    let mut fence: Option<String> = None;
+
+    loop {
+        if fence.is_none() {
+            rest = rest.trim_start_matches('\n');
+        }
+
+        if rest.is_empty() {
+            break;
+        }
+
+        let prefix = fence
+            .as_ref()
+            .map(|fence| format!("{fence}\n"))
+            .unwrap_or_default();
+
+        if prefix.chars().count() + rest.chars().count() <= limit {
+            pieces.push(format!("{prefix}{rest}"));
+            break;
+        }
+
+        let budget = limit
+            .saturating_sub(prefix.chars().count() + CLOSE_FENCE.len())
+            .max(1);
+
+        let (body, remainder) = cut(rest, budget);
+
+        for line in body.lines() {
+            if line.trim_start().starts_with("```") {
+                fence = match fence {
+                    Some(_) => None,
+                    None => Some(line.trim().to_string()),
+                };
+            }
+        }
+
+        // whitespace at the end of a code block may be part of the code
+        let body = if fence.is_some() {
+            body
+        } else {
+            body.trim_end()
+        };
+
+        // a piece holding nothing but fences would render as an empty code block
+        let content = body
+            .lines()
+            .any(|line| !line.trim().is_empty() && !line.trim_start().starts_with("```"));
+
+        if content {
+            let suffix = if fence.is_some() { CLOSE_FENCE } else { "" };
+
+            pieces.push(format!("{prefix}{body}{suffix}"));
+        }
+
+        rest = remainder;
+    }
+
+    pieces
+}
+
+/// Cut `text` into a head of at most `budget` characters and the remainder.
+fn cut(text: &str, budget: usize) -> (&str, &str) {
+    let Some((end, _char)) = text.char_indices().nth(budget) else {
+        return (text, "");
+    };
+
+    let window = &text[..end];
+
+    // the further line breaks are blank lines, which only outside of code blocks can be dropped
+    if let Some(index) = window.rfind("\n\n").filter(|index| *index > 0) {
+        return (&text[..index], &text[index + 1..]);
+    }
+
+    if let Some(index) = window.rfind('\n').filter(|index| *index > 0) {
+        return (&text[..index], &text[index + 1..]);
+    }
+
+    if let Some((index, char)) = window
+        .char_indices()
+        .rev()
+        .find(|(index, char)| *index > 0 && char.is_whitespace())
+    {
+        return (
+            &text[..index + char.len_utf8()],
+            &text[index + char.len_utf8()..],
+        );
+    }
+
+    (window, &text[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split;
+    use crate::reply::MAX_LENGTH;
+
+    fn assert_fits(pieces: &[String], limit: usize) {
+        for piece in pieces {
+            assert!(
+                piece.chars().count() <= limit,
+                "piece of {} characters exceeds {limit}",
+                piece.chars().count()
+            );
+            assert!(!piece.trim().is_empty(), "empty piece");
+        }
+    }
+
+    /// The lines of `pieces` without the fences opening and closing them.
+    fn code_lines(pieces: &[String]) -> Vec<&str> {
+        pieces
+            .iter()
+            .flat_map(|piece| piece.lines())
+            .filter(|line| !line.starts_with("```"))
+            .collect()
+    }
+
+    #[test]
+    fn short() {
+        assert_eq!(split("  hello\n", MAX_LENGTH), ["hello"]);
+        assert!(split("\n \n", MAX_LENGTH).is_empty());
+    }
+
+    #[test]
+    fn limit() {
+        let content = "word ".repeat(1000);
+        let pieces = split(&content, MAX_LENGTH);
+
+        assert_eq!(pieces.len(), 3);
+        assert_fits(&pieces, MAX_LENGTH);
+        assert_eq!(
+            pieces
+                .iter()
+                .flat_map(|piece| piece.split_whitespace())
+                .count(),
+            1000
+        );
+
+        let content = "a".repeat(MAX_LENGTH);
+
+        assert_eq!(split(&content, MAX_LENGTH), [content]);
+    }
+
+    #[test]
+    fn paragraphs() {
+        let paragraph = "x".repeat(15);
+        let content = format!("{paragraph}\n\n{paragraph}\nline\n\n\n{paragraph}");
+        let pieces = split(&content, 30);
+
+        assert_eq!(
+            pieces,
+            [paragraph.clone(), format!("{paragraph}\nline"), paragraph]
+        );
+    }
+
+    #[test]
+    fn fences() {
+        let code = (0..200)
+            .map(|line| format!("let x{line} = {line};"))
+            .collect::<Vec<_>>();
+
+        let content = format!("before\n```rust\n{}\n```\nafter", code.join("\n"));
+        let pieces = split(&content, 500);
+
+        assert!(pieces.len() > 1);
+        assert_fits(&pieces, 500);
+
+        for piece in &pieces[1..pieces.len() - 1] {
+            assert!(piece.starts_with("```rust\n"), "not reopened: {piece:?}");
+            assert!(piece.ends_with("\n```"), "not closed: {piece:?}");
+        }
+
+        let mut lines = vec!["before"];
+        lines.extend(code.iter().map(String::as_str));
+        lines.push("after");
+
+        assert_eq!(code_lines(&pieces), lines);
+    }
+
+    #[test]
+    fn blank_lines_in_code() {
+        let pieces = split("```\naaaa\n\nbbbb\n```", 14);
+
+        assert_eq!(pieces, ["```\naaaa\n```", "```\n\nbbbb\n```"]);
+        assert_eq!(code_lines(&pieces), ["aaaa", "", "bbbb"]);
+    }
+
+    #[test]
+    fn no_empty_pieces() {
+        let content = format!("```\n```\n\n\n\n{}\n\n\n```\n\n```", "text ".repeat(50));
+        let pieces = split(&content, 40);
+
+        assert_fits(&pieces, 40);
+        assert!(pieces.iter().all(|piece| piece.contains("text")));
+    }
+
+    #[test]
+    fn multibyte() {
+        let content = "漢字".repeat(1500);
+        let pieces = split(&content, MAX_LENGTH);
+
+        assert_eq!(pieces.len(), 2);
+        assert_fits(&pieces, MAX_LENGTH);
+        assert_eq!(pieces.concat(), content);
+
+        let content = "👍🏽 ".repeat(1000);
+        let pieces = split(&content, MAX_LENGTH);
+
+        assert_fits(&pieces, MAX_LENGTH);
+        assert_eq!(
+            pieces
+                .iter()
+                .flat_map(|piece| piece.split_whitespace())
+                .count(),
+            1000
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! Gemini function `Schema`s derived from Rust types.
+//!
+//! Use [`schema!`](crate::schema!) to declare argument structs and enums, the generated schema
+//! is then guaranteed to match what `serde` will accept.
+
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{Schema, Type};
+use twilight_model::id::Id;
+
+/// Types that can describe themselves as a Gemini `Schema`.
+pub trait ToSchema {
+    /// Whether a field of this type must be present in an object.
+    const REQUIRED: bool = true;
+
+    fn schema() -> Schema;
+}
+
+pub fn new_schema(kind: Type) -> Schema {
+    let mut schema = Schema::default();
+
+    schema.set_type(kind);
+    schema
+}
+
+/// Join doc comment lines into a single description.
+pub fn description(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Prefix a doc comment description onto `schema`.
+pub fn describe(mut schema: Schema, lines: &[&str]) -> Schema {
+    let description = description(lines);
+
+    if schema.description.is_empty() {
+        schema.description = description;
+    } else if !description.is_empty() {
+        schema.description = format!("{description} ({})", schema.description);
+    }
+
+    schema
+}
+
+/// Convert a variant identifier to the name `#[serde(rename_all = "snake_case")]` uses.
+pub fn snake_case(ident: &str) -> String {
+    let mut name = String::with_capacity(ident.len() + 4);
+
+    for (index, char) in ident.char_indices() {
+        if char.is_uppercase() {
+            if index != 0 {
+                name.push('_');
+            }
+
+            name.extend(char.to_lowercase());
+        } else {
+            name.push(char);
+        }
+    }
+
+    name
+}
+
+impl ToSchema for String {
+    fn schema() -> Schema {
+        new_schema(Type::String)
+    }
+}
+
+impl ToSchema for bool {
+    fn schema() -> Schema {
+        new_schema(Type::Boolean)
+    }
+}
+
+macro_rules! impl_integer {
+    ($($ty:ty => $format:literal,)*) => {
+        $(
+            impl ToSchema for $ty {
+                fn schema() -> Schema {
+                    Schema {
+                        format: String::from($format),
+                        ..new_schema(Type::Integer)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_integer! {
+    i8 => "int32",
+    i16 => "int32",
+    i32 => "int32",
+    i64 => "int64",
+    u8 => "int32",
+    u16 => "int32",
+    u32 => "int64",
+    u64 => "int64",
+}
+
+impl ToSchema for f32 {
+    fn schema() -> Schema {
+        Schema {
+            format: String::from("float"),
+            ..new_schema(Type::Number)
+        }
+    }
+}
+
+impl ToSchema for f64 {
+    fn schema() -> Schema {
+        Schema {
+            format: String::from("double"),
+            ..new_schema(Type::Number)
+        }
+    }
+}
+
+/// Snowflakes are passed as strings, numbers this large lose precision as JSON numbers.
+impl<T> ToSchema for Id<T> {
+    fn schema() -> Schema {
+        new_schema(Type::String)
+    }
+}
+
+impl<T: ToSchema> ToSchema for Option<T> {
+    const REQUIRED: bool = false;
+
+    fn schema() -> Schema {
+        Schema {
+            nullable: true,
+            ..T::schema()
+        }
+    }
+}
+
+impl<T: ToSchema> ToSchema for Box<T> {
+    const REQUIRED: bool = T::REQUIRED;
+
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<T: ToSchema> ToSchema for Vec<T> {
+    fn schema() -> Schema {
+        Schema {
+            items: Some(Box::new(T::schema())),
+            ..new_schema(Type::Array)
+        }
+    }
+}
+
+/// Declare a struct or a fieldless enum along with its [`ToSchema`] implementation.
+///
+/// Doc comments on fields and variants become descriptions, `Option` fields are not required.
+/// Field attributes other than docs are limited to `#[serde(rename = "..")]` and
+/// `#[serde(default)]`, in any order, which rename the property and make it optional. Anything
+/// else, including `#[serde(..)]` on the struct or enum itself, is a compile error, as the schema
+/// could not reflect it. Enums are always `#[serde(rename_all = "snake_case")]`, so they must
+/// derive `Deserialize` or `Serialize` themselves.
+///
+/// ```ignore
+/// schema! {
+///     #[derive(Deserialize)]
+///     struct SendMessageArgs {
+///         /// the channel to send the message in
+///         channel_id: Id<ChannelMarker>,
+///         content: String,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! schema {
+    (
+        $(#[$($attr:tt)*])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$($field_attr:tt)*])*
+                $field_vis:vis $field:ident: $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$($attr)*])*
+        $vis struct $name {
+            $(
+                $(#[$($field_attr)*])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        const _: () = {
+            $($crate::schema_container_attribute!($($attr)*);)*
+        };
+
+        impl $crate::schema::ToSchema for $name {
+            fn schema() -> $crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Schema {
+                use $crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Type;
+                use $crate::schema::ToSchema;
+
+                let mut schema = $crate::schema::new_schema(Type::Object);
+
+                $({
+                    #[allow(unused_mut, unused_assignments)]
+                    let mut name = String::from(stringify!($field));
+                    #[allow(unused_mut, unused_assignments)]
+                    let mut required = <$ty as ToSchema>::REQUIRED;
+                    let docs: &[Option<&str>] = &[
+                        $($crate::schema_field_attribute!(name, required, $($field_attr)*)),*
+                    ];
+                    let docs = docs.iter().flatten().copied().collect::<Vec<_>>();
+
+                    schema.properties.insert(
+                        name.clone(),
+                        $crate::schema::describe(<$ty as ToSchema>::schema(), &docs),
+                    );
+
+                    if required {
+                        schema.required.push(name);
+                    }
+                })*
+
+                schema
+            }
+        }
+    };
+    (
+        $(#[$($attr:tt)*])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $variant:ident
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$($attr)*])*
+        #[serde(rename_all = "snake_case")]
+        $vis enum $name {
+            $(
+                $(#[doc = $doc])*
+                $variant,
+            )*
+        }
+
+        const _: () = {
+            $($crate::schema_container_attribute!($($attr)*);)*
+        };
+
+        impl $crate::schema::ToSchema for $name {
+            fn schema() -> $crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Schema {
+                use $crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{
+                    Schema, Type,
+                };
+
+                let variants: &[(&str, String)] = &[
+                    $((
+                        stringify!($variant),
+                        $crate::schema::description(&[$($doc),*]),
+                    )),*
+                ];
+
+                let values = variants
+                    .iter()
+                    .map(|(variant, _description)| $crate::schema::snake_case(variant))
+                    .collect::<Vec<_>>();
+
+                let description = values
+                    .iter()
+                    .zip(variants)
+                    .filter(|(_value, (_variant, description))| !description.is_empty())
+                    .map(|(value, (_variant, description))| format!("`{value}`: {description}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                Schema {
+                    format: String::from("enum"),
+                    description,
+                    r#enum: values,
+                    ..$crate::schema::new_schema(Type::String)
+                }
+            }
+        }
+    };
+}
+
+/// Reject `#[serde(..)]` on a [`schema!`](crate::schema!) struct or enum, other attributes pass.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! schema_container_attribute {
+    (serde $($serde:tt)*) => {
+        compile_error!(concat!(
+            "schema! cannot reflect #[serde",
+            stringify!($($serde)*),
+            "] on the struct or enum in the schema",
+        ));
+    };
+    ($($other:tt)*) => {};
+}
+
+/// Apply an attribute of a [`schema!`](crate::schema!) field to its property, returning the line
+/// of its description if it is a doc comment.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! schema_field_attribute {
+    ($name:ident, $required:ident, doc = $doc:literal) => {
+        Some($doc)
+    };
+    ($name:ident, $required:ident, serde($($serde:tt)*)) => {{
+        $crate::schema_serde_attribute!($name, $required, $($serde)*);
+
+        None
+    }};
+    ($name:ident, $required:ident, $($other:tt)*) => {
+        compile_error!(concat!(
+            "schema! cannot reflect #[",
+            stringify!($($other)*),
+            "] in the schema",
+        ));
+    };
+}
+
+/// Apply the contents of a field's `#[serde(..)]` attribute to its property.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! schema_serde_attribute {
+    ($name:ident, $required:ident $(,)?) => {};
+    ($name:ident, $required:ident, rename = $rename:literal $(, $($rest:tt)*)?) => {
+        $name = String::from($rename);
+        $($crate::schema_serde_attribute!($name, $required, $($rest)*);)?
+    };
+    ($name:ident, $required:ident, default $(= $default:literal)? $(, $($rest:tt)*)?) => {
+        $required = false;
+        $($crate::schema_serde_attribute!($name, $required, $($rest)*);)?
+    };
+    ($name:ident, $required:ident, $($other:tt)*) => {
+        compile_error!(concat!(
+            "schema! cannot reflect #[serde(",
+            stringify!($($other)*),
+            ")] in the schema",
+        ));
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::ToSchema;
+    use crate::value;
+    use prost_types::Struct;
+    use prost_types::value::Kind;
+    use serde::Deserialize;
+    use twilight_model::id::Id;
+    use twilight_model::id::marker::ChannelMarker;
+
+    schema! {
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Mode {
+            /// only once
+            Once,
+            RepeatForever,
+        }
+    }
+
+    schema! {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Args {
+            /// the channel
+            channel_id: Id<ChannelMarker>,
+            note: Option<String>,
+            #[serde(rename = "type")]
+            kind: Mode,
+            #[serde(default)]
+            /// how often
+            count: u32,
+            #[serde(default, rename = "tags")]
+            labels: Vec<String>,
+        }
+    }
+
+    #[test]
+    fn properties() {
+        let schema = Args::schema();
+
+        let mut properties = schema.properties.keys().collect::<Vec<_>>();
+        properties.sort();
+
+        assert_eq!(properties, ["channel_id", "count", "note", "tags", "type"]);
+        assert_eq!(schema.required, ["channel_id", "type"]);
+        assert_eq!(schema.properties["count"].description, "how often");
+    }
+
+    #[test]
+    fn enums() {
+        let schema = Mode::schema();
+
+        assert_eq!(schema.r#enum, ["once", "repeat_forever"]);
+        assert_eq!(schema.description, "`once`: only once");
+    }
+
+    #[test]
+    fn matches_serde() {
+        let mut args = Struct::default();
+
+        for (key, kind) in [
+            ("channel_id", Kind::StringValue(String::from("1"))),
+            ("type", Kind::StringValue(String::from("repeat_forever"))),
+        ] {
+            args.fields
+                .insert(key.to_string(), prost_types::Value { kind: Some(kind) });
+        }
+
+        // exactly the required properties are enough
+        assert_eq!(
+            value::from_struct::<Args>(args),
+            Ok(Args {
+                channel_id: Id::new(1),
+                note: None,
+                kind: Mode::RepeatForever,
+                count: 0,
+                labels: Vec::new(),
+            })
+        );
+    }
+}
@@ -0,0 +1,820 @@
+//! Drives a Gemini Live session independently of the Discord event loop.
+//!
+//! The Discord side only ever pushes [`Input`]s into a channel, the driver task owns the bidi
+//! stream and decides when to start a turn. Inputs arriving while the model is busy are queued
+//! and sent together as the next turn. Ambient inputs are sent without completing the turn, so
+//! they only become context for the next one.
+//!
+//! Media is downloaded and converted by a task of its own before inputs reach the driver, so a
+//! slow download does not hold up the stream. Media of ambient inputs is only named, unless
+//! `media.ambient` is set.
+//!
+//! When the stream fails the driver reconnects with exponential backoff. The pinned API revision
+//! has no session resumption handles, so the context is rebuilt by replaying recent turns.
+
+use crate::State;
+use crate::gemini::GeminiLive;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::bidi_generate_content_client_message::MessageType as ClientMessageType;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::bidi_generate_content_server_message::MessageType;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::part::Data;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{
+    BidiGenerateContentClientContent, BidiGenerateContentClientMessage,
+    BidiGenerateContentServerContent, BidiGenerateContentServerMessage, BidiGenerateContentSetup,
+    CodeExecution, Content, Part, Tool,
+};
+use crate::media::{self, Media, audio};
+use crate::options::{AudioFormat, ReplyMode};
+use crate::reply::{self, StreamingReply};
+use anyhow::Context;
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, TryStreamExt};
+use std::collections::VecDeque;
+use std::iter;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task;
+use tokio::time::{self, Instant};
+use tonic::Status;
+use tracing::{debug, info, warn};
+use twilight_model::channel::message::AllowedMentions;
+use twilight_model::http::attachment::Attachment;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, MessageMarker, UserMarker};
+
+pub use self::manager::{Scope, SessionKey, SessionManager};
+
+mod manager;
+
+/// How many turns are kept to rebuild the context after reconnecting.
+const MAX_HISTORY: usize = 64;
+
+/// Delay before the first reconnection attempt, doubled on every failure.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Sample rate of the model's spoken output, unless its mime type says otherwise.
+pub const OUTPUT_SAMPLE_RATE: u32 = 24_000;
+
+/// Something for the model to read.
+#[derive(Clone, Debug)]
+pub struct Input {
+    pub parts: Vec<Part>,
+    /// The channel this input came from, direct replies are posted here.
+    pub channel_id: Option<Id<ChannelMarker>>,
+    /// The message this input is.
+    pub message_id: Option<Id<MessageMarker>>,
+    /// The user whose message this input is.
+    pub author_id: Option<Id<UserMarker>>,
+    /// The author and users mentioned by the message.
+    pub user_ids: Vec<Id<UserMarker>>,
+    /// Whether the model should respond, otherwise the input is only added to the context.
+    pub respond: bool,
+    /// Downloaded and converted into parts before the input reaches the driver.
+    pub media: Vec<Media>,
+}
+
+impl Input {
+    pub fn text(text: String) -> Self {
+        Self {
+            parts: vec![Part {
+                data: Some(Data::Text(text)),
+            }],
+            channel_id: None,
+            message_id: None,
+            author_id: None,
+            user_ids: Vec::new(),
+            respond: true,
+            media: Vec::new(),
+        }
+    }
+
+    pub fn in_channel(mut self, channel_id: Id<ChannelMarker>) -> Self {
+        self.channel_id = Some(channel_id);
+        self
+    }
+
+    pub fn with_message_id(mut self, message_id: Id<MessageMarker>) -> Self {
+        self.message_id = Some(message_id);
+        self
+    }
+
+    /// Attribute this input to a message by `author_id` mentioning `user_ids`.
+    pub fn by(
+        mut self,
+        author_id: Id<UserMarker>,
+        user_ids: impl IntoIterator<Item = Id<UserMarker>>,
+    ) -> Self {
+        self.author_id = Some(author_id);
+        self.user_ids = iter::once(author_id).chain(user_ids).collect();
+        self
+    }
+
+    pub fn with_media(mut self, media: Vec<Media>) -> Self {
+        self.media = media;
+        self
+    }
+
+    /// Add this input to the context without having the model respond.
+    pub fn ambient(mut self) -> Self {
+        self.respond = false;
+        self
+    }
+}
+
+/// What the model is currently responding to.
+#[derive(Clone, Debug, Default)]
+pub struct Turn {
+    /// The channel of the most recent input, direct replies are posted here.
+    pub channel_id: Option<Id<ChannelMarker>>,
+    /// The message of the most recent input, if it was one.
+    pub message_id: Option<Id<MessageMarker>>,
+    /// The authors of every input, all of whom the turn acts for.
+    pub author_ids: Vec<Id<UserMarker>>,
+    /// The authors and users mentioned by every input.
+    pub user_ids: Vec<Id<UserMarker>>,
+}
+
+impl Turn {
+    /// The turn responding to `inputs`, ambient inputs have no say in it.
+    fn new(inputs: &[Input]) -> Self {
+        let inputs = inputs.iter().filter(|input| input.respond);
+        let mut author_ids = Vec::new();
+        let mut user_ids = Vec::new();
+
+        for author_id in inputs.clone().filter_map(|input| input.author_id) {
+            if !author_ids.contains(&author_id) {
+                author_ids.push(author_id);
+            }
+        }
+
+        for user_id in inputs.clone().flat_map(|input| &input.user_ids) {
+            if !user_ids.contains(user_id) {
+                user_ids.push(*user_id);
+            }
+        }
+
+        let latest = inputs.rev().find(|input| input.channel_id.is_some());
+
+        Self {
+            channel_id: latest.and_then(|input| input.channel_id),
+            message_id: latest.and_then(|input| input.message_id),
+            author_ids,
+            user_ids,
+        }
+    }
+
+    /// The mentions allowed in messages posted during this turn.
+    pub fn allowed_mentions(&self, state: &State) -> AllowedMentions {
+        state
+            .options
+            .discord
+            .mentions
+            .allowed_mentions(&self.user_ids)
+    }
+}
+
+/// Handle to a running session driver.
+#[derive(Clone)]
+pub struct Session {
+    inputs: UnboundedSender<Input>,
+}
+
+impl Session {
+    /// Spawn a driver task for a new session covering `description`.
+    pub fn spawn(state: Arc<State>, description: String) -> Self {
+        let (inputs, receiver) = mpsc::unbounded_channel();
+        let (prepared, prepared_receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(prepare(state.clone(), receiver, prepared));
+        tokio::spawn(async move {
+            if let Err(error) = run(state, description, prepared_receiver).await {
+                warn!("gemini session ended: {error}");
+            }
+        });
+
+        Self { inputs }
+    }
+
+    /// Queue an input for the model, handing it back if the driver has stopped.
+    pub fn send(&self, input: Input) -> Result<(), Input> {
+        self.inputs.send(input).map_err(|error| error.0)
+    }
+
+    /// Whether the driver has stopped, either due to an error or being idle.
+    pub fn is_closed(&self) -> bool {
+        self.inputs.is_closed()
+    }
+}
+
+/// Turn the media of `inputs` into parts, in order, until the driver stops.
+async fn prepare(
+    state: Arc<State>,
+    mut inputs: UnboundedReceiver<Input>,
+    prepared: UnboundedSender<Input>,
+) {
+    loop {
+        let input = tokio::select! {
+            () = prepared.closed() => return,
+            input = inputs.recv() => input,
+        };
+
+        let Some(mut input) = input else {
+            return;
+        };
+
+        let media = mem::take(&mut input.media);
+
+        if input.respond || state.options.media.ambient {
+            input.parts.extend(media::parts(&state, &media).await);
+        } else {
+            input.parts.extend(media::labels(&media));
+        }
+
+        if prepared.send(input).is_err() {
+            return;
+        }
+    }
+}
+
+/// Build the setup message sent at the start of every session.
+pub fn setup(state: &State, description: &str) -> BidiGenerateContentSetup {
+    let system_instructions = format!(
+        "{}\n\nThis conversation covers {description}.",
+        state.options.gemini.system_instructions
+    );
+
+    BidiGenerateContentSetup {
+        model: format!("models/{}", state.options.gemini.model),
+        generation_config: Some(state.options.gemini.generation_config()),
+        system_instruction: Some(Content {
+            parts: vec![Part {
+                data: Some(Data::Text(system_instructions)),
+            }],
+            ..Default::default()
+        }),
+        tools: vec![
+            Tool {
+                code_execution: Some(CodeExecution {}),
+                ..Default::default()
+            },
+            state.tools.declarations(),
+        ],
+    }
+}
+
+pub fn client_message(message_type: ClientMessageType) -> BidiGenerateContentClientMessage {
+    BidiGenerateContentClientMessage {
+        message_type: Some(message_type),
+    }
+}
+
+/// The state of a session that survives reconnecting.
+struct Driver {
+    state: Arc<State>,
+    description: String,
+    inputs: UnboundedReceiver<Input>,
+    /// Inputs waiting for the current turn to complete.
+    pending: Vec<Input>,
+    /// Recent turns, replayed to rebuild the context after reconnecting.
+    history: VecDeque<Content>,
+    /// Text the model produced so far in the current turn.
+    reply: Vec<Part>,
+    /// 16 bit PCM the model spoke so far in the current turn, in the audio modality.
+    audio: Vec<u8>,
+    audio_sample_rate: u32,
+    /// Whom the current turn responds to.
+    turn: Turn,
+    /// The reply being edited as it is generated, in streaming reply mode.
+    streaming: Option<StreamingReply>,
+    in_turn: bool,
+}
+
+/// A connected bidi stream.
+pub struct Connection {
+    pub sender: UnboundedSender<BidiGenerateContentClientMessage>,
+    /// Boxed, so tests can stand in for the server.
+    pub stream: BoxStream<'static, Result<BidiGenerateContentServerMessage, Status>>,
+}
+
+impl Connection {
+    /// Open a bidi stream and wait for the server to accept `setup`.
+    pub async fn open(api_key: String, setup: BidiGenerateContentSetup) -> anyhow::Result<Self> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        sender.send(client_message(ClientMessageType::Setup(setup)))?;
+
+        let mut gemini = GeminiLive::connect(api_key).await?;
+        let mut stream = gemini.bidi(receiver).await?.boxed();
+
+        // the first message acknowledges the setup
+        stream.try_next().await?;
+
+        Ok(Self { sender, stream })
+    }
+}
+
+/// Delay before reconnecting, doubled on every attempt that fails.
+pub struct Backoff {
+    delay: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self { delay: MIN_BACKOFF }
+    }
+}
+
+impl Backoff {
+    /// Start over after connecting successfully.
+    pub fn reset(&mut self) {
+        self.delay = MIN_BACKOFF;
+    }
+
+    pub async fn wait(&mut self) {
+        info!("reconnect in {:?}", self.delay);
+        time::sleep(self.delay).await;
+        self.delay = (self.delay * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Why [`Driver::drive`] returned without an error.
+enum Stop {
+    Idle,
+    Dropped,
+}
+
+async fn run(
+    state: Arc<State>,
+    description: String,
+    inputs: UnboundedReceiver<Input>,
+) -> anyhow::Result<()> {
+    let mut driver = Driver {
+        state,
+        description,
+        inputs,
+        pending: Vec::new(),
+        history: VecDeque::new(),
+        reply: Vec::new(),
+        audio: Vec::new(),
+        audio_sample_rate: OUTPUT_SAMPLE_RATE,
+        turn: Turn::default(),
+        streaming: None,
+        in_turn: false,
+    };
+
+    let mut backoff = Backoff::default();
+
+    loop {
+        match driver.connect().await {
+            Ok(connection) => {
+                backoff.reset();
+
+                match driver.drive(connection).await {
+                    Ok(Stop::Idle) => {
+                        info!(
+                            "session covering {} is idle, stop session",
+                            driver.description
+                        );
+
+                        return Ok(());
+                    }
+                    Ok(Stop::Dropped) => {
+                        info!("all session handles dropped, stop session");
+
+                        return Ok(());
+                    }
+                    Err(error) => warn!("lost session covering {}: {error}", driver.description),
+                }
+            }
+            Err(error) => warn!(
+                "failed to connect session covering {}: {error}",
+                driver.description
+            ),
+        }
+
+        if driver.inputs.is_closed() && driver.pending.is_empty() {
+            return Ok(());
+        }
+
+        backoff.wait().await;
+    }
+}
+
+impl Driver {
+    async fn connect(&mut self) -> anyhow::Result<Connection> {
+        let connection = Connection::open(
+            self.state.options.gemini.api_key.clone(),
+            setup(&self.state, &self.description),
+        )
+        .await?;
+
+        // the previous stream is gone along with its context, replay what we remember
+        if !self.history.is_empty() {
+            info!("replay {} turns of history", self.history.len());
+
+            self.reply.clear();
+            self.audio.clear();
+            self.audio_sample_rate = OUTPUT_SAMPLE_RATE;
+
+            // the replayed turn is generated from scratch, start a fresh message for it
+            if let Some(streaming) = self.streaming.take() {
+                let channel_id = streaming.channel_id();
+
+                if let Err(error) = streaming.finish(&self.state, true).await {
+                    warn!("failed to finish streaming reply: {error}");
+                }
+
+                self.streaming = Some(StreamingReply::new(
+                    channel_id,
+                    self.turn.allowed_mentions(&self.state),
+                ));
+            }
+
+            connection
+                .sender
+                .send(client_message(ClientMessageType::ClientContent(
+                    BidiGenerateContentClientContent {
+                        turns: self.history.iter().cloned().collect(),
+                        turn_complete: self.in_turn,
+                    },
+                )))?;
+        }
+
+        Ok(connection)
+    }
+
+    async fn drive(&mut self, mut connection: Connection) -> anyhow::Result<Stop> {
+        let idle_timeout = Duration::from_secs(self.state.options.gemini.idle_timeout);
+
+        loop {
+            let deadline = Instant::now() + idle_timeout;
+            let idle = !self.in_turn && self.pending.is_empty();
+            let flush = self.streaming.as_ref().and_then(StreamingReply::deadline);
+
+            tokio::select! {
+                _ = time::sleep_until(deadline), if idle => return Ok(Stop::Idle),
+                _ = time::sleep_until(flush.unwrap_or(deadline)), if flush.is_some() => {
+                    if let Some(streaming) = &mut self.streaming
+                        && let Err(error) = streaming.flush(&self.state).await
+                    {
+                        warn!("failed to update streaming reply: {error}");
+                    }
+                }
+                input = self.inputs.recv() => {
+                    let Some(input) = input else {
+                        return Ok(Stop::Dropped);
+                    };
+
+                    self.pending.push(input);
+
+                    while let Ok(input) = self.inputs.try_recv() {
+                        self.pending.push(input);
+                    }
+                }
+                message = connection.stream.try_next() => {
+                    let Some(BidiGenerateContentServerMessage { message_type }) = message? else {
+                        anyhow::bail!("stream closed by server");
+                    };
+
+                    debug!("{message_type:?}");
+
+                    if let Some(message_type) = message_type {
+                        self.handle(&connection.sender, message_type).await?;
+                    }
+                }
+            }
+
+            if !self.in_turn && !self.pending.is_empty() {
+                let respond = self.pending.iter().any(|input| input.respond);
+
+                if respond {
+                    info!("start turn with {} queued inputs", self.pending.len());
+
+                    self.turn = Turn::new(&self.pending);
+
+                    self.streaming = self
+                        .turn
+                        .channel_id
+                        .filter(|_channel_id| {
+                            self.state.options.discord.reply == ReplyMode::Streaming
+                        })
+                        .map(|channel_id| {
+                            StreamingReply::new(channel_id, self.turn.allowed_mentions(&self.state))
+                        });
+                } else {
+                    debug!("add {} ambient inputs to context", self.pending.len());
+                }
+
+                let content = Content {
+                    parts: mem::take(&mut self.pending)
+                        .into_iter()
+                        .flat_map(|input| input.parts)
+                        .collect(),
+                    role: String::from("user"),
+                };
+
+                self.remember(&content);
+
+                connection
+                    .sender
+                    .send(client_message(ClientMessageType::ClientContent(
+                        BidiGenerateContentClientContent {
+                            turns: vec![content],
+                            turn_complete: respond,
+                        },
+                    )))?;
+
+                self.in_turn = respond;
+            }
+        }
+    }
+
+    async fn handle(
+        &mut self,
+        sender: &UnboundedSender<BidiGenerateContentClientMessage>,
+        message_type: MessageType,
+    ) -> anyhow::Result<()> {
+        match message_type {
+            MessageType::ServerContent(BidiGenerateContentServerContent {
+                model_turn,
+                turn_complete,
+                interrupted,
+                ..
+            }) => {
+                if let Some(model_turn) = model_turn {
+                    for part in model_turn.parts {
+                        match &part.data {
+                            Some(Data::Text(text)) => {
+                                if let Some(streaming) = &mut self.streaming
+                                    && let Err(error) = streaming.push(&self.state, text).await
+                                {
+                                    warn!("failed to update streaming reply: {error}");
+                                }
+
+                                self.reply.push(part);
+                            }
+                            Some(Data::InlineData(blob))
+                                if blob.mime_type.starts_with("audio/pcm") =>
+                            {
+                                self.audio_sample_rate = audio::sample_rate(&blob.mime_type)
+                                    .unwrap_or(OUTPUT_SAMPLE_RATE);
+                                self.audio.extend_from_slice(&blob.data);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                if interrupted || turn_complete {
+                    info!("model completed turn");
+
+                    if let Some(streaming) = self.streaming.take()
+                        && let Err(error) = streaming.finish(&self.state, interrupted).await
+                    {
+                        warn!("failed to finish streaming reply: {error}");
+                    }
+
+                    let content = Content {
+                        parts: mem::take(&mut self.reply),
+                        role: String::from("model"),
+                    };
+
+                    // the audio is not remembered, and comes without text to remember instead
+                    if !self.audio.is_empty() {
+                        let audio = mem::take(&mut self.audio);
+
+                        self.post_audio(audio).await;
+                    } else if !content.parts.is_empty() {
+                        self.post_reply(&content).await;
+                    }
+
+                    if !content.parts.is_empty() {
+                        self.remember(&content);
+                    }
+
+                    self.in_turn = false;
+                }
+            }
+            MessageType::ToolCall(tool_call) => {
+                // in the tools reply mode these are all the model says, keep them for replaying
+                let calls = Content {
+                    parts: tool_call
+                        .function_calls
+                        .iter()
+                        .map(|function_call| Part {
+                            data: Some(Data::FunctionCall(function_call.clone())),
+                        })
+                        .collect(),
+                    role: String::from("model"),
+                };
+
+                let tool_response = self
+                    .state
+                    .tools
+                    .dispatch(&self.state, &self.turn, tool_call)
+                    .await;
+
+                let responses = Content {
+                    parts: tool_response
+                        .function_responses
+                        .iter()
+                        .map(|function_response| Part {
+                            data: Some(Data::FunctionResponse(function_response.clone())),
+                        })
+                        .collect(),
+                    role: String::from("user"),
+                };
+
+                self.remember(&calls);
+                self.remember(&responses);
+
+                sender.send(client_message(ClientMessageType::ToolResponse(
+                    tool_response,
+                )))?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Post the model's text output, if direct replies are enabled.
+    async fn post_reply(&self, content: &Content) {
+        if self.state.options.discord.reply != ReplyMode::Direct {
+            return;
+        }
+
+        let Some(channel_id) = self.turn.channel_id else {
+            return;
+        };
+
+        let text = text(content);
+
+        if text.trim().is_empty() {
+            return;
+        }
+
+        let allowed_mentions = self.turn.allowed_mentions(&self.state);
+
+        if let Err(error) = reply::send(&self.state, channel_id, &text, &allowed_mentions).await {
+            warn!("failed to post reply to channel_id={channel_id}: {error}");
+        }
+    }
+
+    /// Upload the model's spoken output.
+    ///
+    /// Audio is posted in every reply mode, as no tool can post it. The live API sends no text
+    /// along with audio, so there is no transcript to post.
+    async fn post_audio(&self, audio: Vec<u8>) {
+        let Some(channel_id) = self.turn.channel_id else {
+            return;
+        };
+
+        let attachment = match encode_audio(
+            audio,
+            self.audio_sample_rate,
+            self.state.options.discord.audio_format,
+        )
+        .await
+        {
+            Ok(attachment) => attachment,
+            Err(error) => {
+                warn!("failed to encode audio reply: {error}");
+
+                return;
+            }
+        };
+
+        if let Err(error) = reply::send_audio(&self.state, channel_id, attachment).await {
+            warn!("failed to post audio reply to channel_id={channel_id}: {error}");
+        }
+    }
+
+    /// Keep `content` to replay after reconnecting.
+    fn remember(&mut self, content: &Content) {
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(without_inline_data(content));
+    }
+}
+
+/// `content` with images and audio replaced by a note, as they are too large to replay with every
+/// reconnect. The text parts naming them are kept.
+fn without_inline_data(content: &Content) -> Content {
+    let parts = content
+        .parts
+        .iter()
+        .map(|part| match &part.data {
+            Some(Data::InlineData(blob)) => Part {
+                data: Some(Data::Text(format!(
+                    "({}, no longer available)",
+                    blob.mime_type
+                ))),
+            },
+            _ => part.clone(),
+        })
+        .collect();
+
+    Content {
+        parts,
+        role: content.role.clone(),
+    }
+}
+
+/// The text parts of `content`.
+fn text(content: &Content) -> String {
+    content
+        .parts
+        .iter()
+        .filter_map(|part| match &part.data {
+            Some(Data::Text(text)) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+async fn encode_audio(
+    audio: Vec<u8>,
+    sample_rate: u32,
+    format: AudioFormat,
+) -> anyhow::Result<Attachment> {
+    let samples = audio::samples(&audio);
+
+    let (filename, bytes) = task::spawn_blocking(move || {
+        anyhow::Ok(match format {
+            AudioFormat::Ogg => ("reply.ogg", audio::encode_ogg(&samples, sample_rate)?),
+            AudioFormat::Wav => ("reply.wav", audio::encode_wav(&samples, sample_rate)),
+        })
+    })
+    .await
+    .context("audio encoding panicked")??;
+
+    Ok(Attachment::from_bytes(filename.to_string(), bytes, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Input, Turn, without_inline_data};
+    use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::part::Data;
+    use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{Blob, Content, Part};
+    use twilight_model::id::Id;
+
+    #[test]
+    fn turn_authors() {
+        let inputs = [
+            Input::text(String::from("a"))
+                .in_channel(Id::new(1))
+                .by(Id::new(10), [Id::new(11)]),
+            Input::text(String::from("b"))
+                .in_channel(Id::new(2))
+                .by(Id::new(20), [])
+                .ambient(),
+            Input::text(String::from("c"))
+                .in_channel(Id::new(3))
+                .with_message_id(Id::new(300))
+                .by(Id::new(30), []),
+            Input::text(String::from("d")).by(Id::new(10), []),
+        ];
+
+        let turn = Turn::new(&inputs);
+
+        assert_eq!(turn.channel_id, Some(Id::new(3)));
+        assert_eq!(turn.message_id, Some(Id::new(300)));
+        assert_eq!(turn.author_ids, [Id::new(10), Id::new(30)]);
+        assert_eq!(turn.user_ids, [Id::new(10), Id::new(11), Id::new(30)]);
+    }
+
+    #[test]
+    fn history_without_inline_data() {
+        let content = Content {
+            parts: vec![
+                Part {
+                    data: Some(Data::Text(String::from("Voice message (1.0 seconds):"))),
+                },
+                Part {
+                    data: Some(Data::InlineData(Blob {
+                        mime_type: String::from("audio/pcm;rate=16000"),
+                        data: vec![0; 32_000],
+                    })),
+                },
+            ],
+            role: String::from("user"),
+        };
+
+        let remembered = without_inline_data(&content);
+
+        assert_eq!(remembered.role, "user");
+        assert_eq!(
+            remembered.parts[1].data,
+            Some(Data::Text(String::from(
+                "(audio/pcm;rate=16000, no longer available)"
+            )))
+        );
+        assert_eq!(remembered.parts[0], content.parts[0]);
+    }
+}
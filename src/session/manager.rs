@@ -0,0 +1,170 @@
+use super::{Input, Session};
+use crate::State;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use twilight_cache_inmemory::DefaultInMemoryCache;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker};
+
+/// How conversations are grouped into Gemini sessions.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// One session for everything ari can see.
+    Global,
+    /// One session per guild.
+    Guild,
+    /// One session per channel, threads share the session of their parent channel.
+    #[default]
+    Channel,
+    /// One session per channel, with a separate session for every thread.
+    Thread,
+}
+
+/// Identifies a single session.
+///
+/// Direct messages always get their own session, unless the scope is global.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SessionKey {
+    Global,
+    Guild(Id<GuildMarker>),
+    Channel(Id<ChannelMarker>),
+    Dm(Id<ChannelMarker>),
+}
+
+impl Scope {
+    /// The session a message in `channel_id` belongs to.
+    pub fn key(
+        self,
+        cache: &DefaultInMemoryCache,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
+    ) -> SessionKey {
+        let Some(guild_id) = guild_id else {
+            return match self {
+                Self::Global => SessionKey::Global,
+                _ => SessionKey::Dm(channel_id),
+            };
+        };
+
+        match self {
+            Self::Global => SessionKey::Global,
+            Self::Guild => SessionKey::Guild(guild_id),
+            Self::Channel => {
+                let parent_id = cache
+                    .channel(channel_id)
+                    .filter(|channel| channel.kind.is_thread())
+                    .and_then(|channel| channel.parent_id);
+
+                SessionKey::Channel(parent_id.unwrap_or(channel_id))
+            }
+            Self::Thread => SessionKey::Channel(channel_id),
+        }
+    }
+}
+
+impl SessionKey {
+    /// Explain to the model what this session covers.
+    pub fn describe(self, cache: &DefaultInMemoryCache) -> String {
+        match self {
+            Self::Global => String::from("every guild, channel and direct message you can see"),
+            Self::Guild(guild_id) => {
+                let name = cache
+                    .guild(guild_id)
+                    .map(|guild| guild.name().to_string())
+                    .unwrap_or_else(|| String::from("unknown"));
+
+                format!("the guild {name:?} (guild_id={guild_id})")
+            }
+            Self::Channel(channel_id) => {
+                let name = cache
+                    .channel(channel_id)
+                    .and_then(|channel| channel.name.clone())
+                    .unwrap_or_else(|| String::from("unknown"));
+
+                format!("the channel #{name} (channel_id={channel_id}) and its threads")
+            }
+            Self::Dm(channel_id) => format!("a direct message (channel_id={channel_id})"),
+        }
+    }
+}
+
+/// Lazily opens one session per [`SessionKey`].
+pub struct SessionManager {
+    state: Arc<State>,
+    sessions: HashMap<SessionKey, Session>,
+}
+
+impl SessionManager {
+    pub fn new(state: Arc<State>) -> Self {
+        Self {
+            state,
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Send `input` to the session for a message in `channel_id`, opening it if necessary.
+    pub fn send(
+        &mut self,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
+        input: Input,
+    ) {
+        let key = self.key(guild_id, channel_id);
+
+        let session = self.sessions.entry(key).or_insert_with(|| {
+            info!("open session for {key:?}");
+
+            Session::spawn(self.state.clone(), key.describe(&self.state.cache))
+        });
+
+        // the session may have gone idle just now, retry once with a fresh one
+        if let Err(input) = session.send(input) {
+            info!("reopen session for {key:?}");
+
+            *session = Session::spawn(self.state.clone(), key.describe(&self.state.cache));
+
+            if session.send(input).is_err() {
+                warn!("session for {key:?} stopped immediately, dropping input");
+            }
+        }
+    }
+
+    /// Add `input` to the session for `channel_id` as context, unless no session is open.
+    ///
+    /// Events nobody is talking about, such as a message being deleted, are not worth a session.
+    pub fn append(
+        &mut self,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
+        input: Input,
+    ) {
+        let key = self.key(guild_id, channel_id);
+
+        let Some(session) = self.sessions.get(&key) else {
+            return;
+        };
+
+        if session.send(input.ambient()).is_err() {
+            debug!("session for {key:?} stopped, dropping input");
+        }
+    }
+
+    /// The key of the session for `channel_id`, forgetting about sessions that stopped.
+    fn key(
+        &mut self,
+        guild_id: Option<Id<GuildMarker>>,
+        channel_id: Id<ChannelMarker>,
+    ) -> SessionKey {
+        // sessions stop themselves once idle, forget about them here
+        self.sessions.retain(|_key, session| !session.is_closed());
+
+        self.state
+            .options
+            .gemini
+            .scope
+            .key(&self.state.cache, guild_id, channel_id)
+    }
+}
@@ -0,0 +1,302 @@
+use crate::State;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{
+    self, BidiGenerateContentToolCall, BidiGenerateContentToolResponse, FunctionDeclaration,
+    FunctionResponse, Schema,
+};
+use crate::session::Turn;
+use crate::value;
+use futures_util::future::BoxFuture;
+use prost_types::Struct;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use tracing::{info, warn};
+use twilight_model::guild::Permissions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::ChannelMarker;
+
+pub use self::delete_message::DeleteMessage;
+pub use self::edit_message::EditMessage;
+pub use self::join_voice::JoinVoice;
+pub use self::leave_voice::LeaveVoice;
+pub use self::permissions::Subject;
+pub use self::react_to_message::ReactToMessage;
+pub use self::send_message::SendMessage;
+
+mod delete_message;
+mod edit_message;
+mod join_voice;
+mod leave_voice;
+mod permissions;
+mod policy;
+mod react_to_message;
+mod send_message;
+
+/// A function the model can call.
+pub trait Tool: Send + Sync {
+    /// The name the model calls this tool by.
+    fn name(&self) -> &'static str;
+
+    /// What this tool does, as explained to the model.
+    fn description(&self) -> &'static str;
+
+    /// Schema of the arguments object.
+    fn parameters(&self) -> Schema;
+
+    /// Permissions needed in the channel given by the `channel_id` argument, checked before the
+    /// tool is called.
+    fn permissions(&self) -> Permissions {
+        Permissions::empty()
+    }
+
+    /// Execute this tool with the arguments provided by the model during `turn`.
+    fn call<'a>(
+        &'a self,
+        state: &'a State,
+        turn: &'a Turn,
+        args: Struct,
+    ) -> BoxFuture<'a, anyhow::Result<Struct>>;
+}
+
+/// The set of tools exposed to the model.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<&'static str, Box<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a tool, replacing any existing tool with the same name.
+    pub fn with(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.insert(tool.name(), Box::new(tool));
+        self
+    }
+
+    /// The function declarations to send in `BidiGenerateContentSetup`.
+    pub fn declarations(&self) -> v1alpha::Tool {
+        let function_declarations = self
+            .tools
+            .values()
+            .map(|tool| FunctionDeclaration {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                parameters: Some(tool.parameters()),
+                ..Default::default()
+            })
+            .collect();
+
+        v1alpha::Tool {
+            function_declarations,
+            ..Default::default()
+        }
+    }
+
+    /// Execute every function call in `tool_call`.
+    pub async fn dispatch(
+        &self,
+        state: &State,
+        turn: &Turn,
+        tool_call: BidiGenerateContentToolCall,
+    ) -> BidiGenerateContentToolResponse {
+        let mut function_responses = Vec::with_capacity(tool_call.function_calls.len());
+
+        for function_call in tool_call.function_calls {
+            info!("model executed {} tool", function_call.name);
+
+            let response = match self.tools.get(&*function_call.name) {
+                Some(tool) => {
+                    let args = function_call.args.unwrap_or_default();
+
+                    let hint = hint(turn, &args);
+
+                    // arguments the tool rejects are reported when it parses them
+                    let channel_id = args
+                        .fields
+                        .get("channel_id")
+                        .and_then(|channel_id| value::from_value(channel_id.clone()).ok());
+
+                    let permitted = channel_id.map_or(Ok(()), |channel_id| {
+                        permissions::check(state, turn, channel_id, tool.permissions())
+                    });
+
+                    let result = match permitted {
+                        Ok(()) => tool.call(state, turn, args).await,
+                        Err(error) => Err(error.into()),
+                    };
+
+                    match result {
+                        Ok(response) => response,
+                        Err(error) => {
+                            warn!("{} failed: {error}", function_call.name);
+
+                            ToolError::from(error).into_response(hint)
+                        }
+                    }
+                }
+                None => {
+                    warn!("unknown tool `{}`", function_call.name);
+
+                    ToolError::UnknownTool {
+                        name: function_call.name.clone(),
+                    }
+                    .into_response(None)
+                }
+            };
+
+            function_responses.push(FunctionResponse {
+                id: function_call.id,
+                name: function_call.name,
+                response: Some(response),
+            });
+        }
+
+        BidiGenerateContentToolResponse { function_responses }
+    }
+}
+
+/// Why a tool call failed, reported back to the model so it can correct itself.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolError {
+    UnknownTool {
+        name: String,
+    },
+    MissingArgument {
+        field: String,
+    },
+    UnknownArgument {
+        field: String,
+    },
+    InvalidArgument {
+        field: String,
+        message: String,
+    },
+    MissingPermissions {
+        subject: Subject,
+        permissions: Vec<String>,
+        channel_id: Id<ChannelMarker>,
+    },
+    Forbidden {
+        reason: String,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: ToolError,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl ToolError {
+    /// The function response reporting this error, along with a `hint` on how to try again.
+    pub fn into_response(self, hint: Option<String>) -> Struct {
+        value::to_struct(&ErrorResponse { error: self, hint })
+            .expect("a struct serializes to a struct")
+    }
+}
+
+/// Point the model at the message `turn` responds to when a call in some channel failed, should
+/// it have the ids wrong.
+fn hint(turn: &Turn, args: &Struct) -> Option<String> {
+    if !args.fields.contains_key("channel_id") {
+        return None;
+    }
+
+    let channel_id = turn.channel_id?;
+
+    Some(match turn.message_id {
+        Some(message_id) => format!(
+            "the message you are responding to is channel_id={channel_id} message_id={message_id}, maybe try again with those"
+        ),
+        None => format!("you are responding in channel_id={channel_id}, maybe try again with it"),
+    })
+}
+
+impl From<anyhow::Error> for ToolError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<value::Error>() {
+            Ok(error) => return Self::from(error),
+            Err(error) => error,
+        };
+
+        match error.downcast::<ToolError>() {
+            Ok(error) => error,
+            Err(error) => Self::Failed {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl From<value::Error> for ToolError {
+    fn from(error: value::Error) -> Self {
+        let field = error.field();
+
+        match error.kind() {
+            value::ErrorKind::MissingField(_) => Self::MissingArgument { field },
+            value::ErrorKind::UnknownField(_) => Self::UnknownArgument { field },
+            value::ErrorKind::Custom(message) => Self::InvalidArgument {
+                field,
+                message: message.clone(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTool { name } => write!(fmt, "unknown tool `{name}`"),
+            Self::MissingArgument { field } => write!(fmt, "missing argument `{field}`"),
+            Self::UnknownArgument { field } => write!(fmt, "unknown argument `{field}`"),
+            Self::InvalidArgument { field, message } => {
+                write!(fmt, "invalid argument `{field}`: {message}")
+            }
+            Self::MissingPermissions {
+                subject,
+                permissions,
+                channel_id,
+            } => {
+                let subject = match subject {
+                    Subject::You => "you are",
+                    Subject::User => "the user who asked is",
+                };
+
+                write!(
+                    fmt,
+                    "{subject} missing permission {} in channel_id={channel_id}",
+                    permissions.join(", "),
+                )
+            }
+            Self::Forbidden { reason } => write!(fmt, "not allowed: {reason}"),
+            Self::Failed { message } => fmt.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+#[derive(Serialize)]
+struct Output {
+    output: String,
+}
+
+/// Format ids as a comma separated list for tool output.
+fn join<T>(ids: &[Id<T>]) -> String {
+    ids.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Wrap a status message in a function response object.
+pub fn output(output: String) -> Struct {
+    value::to_struct(&Output { output }).expect("a struct serializes to a struct")
+}
@@ -0,0 +1,74 @@
+use super::{Tool, output, policy};
+use crate::State;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Schema;
+use crate::schema::ToSchema;
+use crate::session::Turn;
+use crate::{schema, value};
+use futures_util::future::BoxFuture;
+use prost_types::Struct;
+use serde::Deserialize;
+use tracing::info;
+use twilight_model::guild::Permissions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, MessageMarker};
+
+pub struct DeleteMessage;
+
+schema! {
+    #[derive(Deserialize)]
+    struct DeleteMessageArgs {
+        /// the channel the message is in
+        channel_id: Id<ChannelMarker>,
+        /// the message to delete
+        message_id: Id<MessageMarker>,
+    }
+}
+
+impl Tool for DeleteMessage {
+    fn name(&self) -> &'static str {
+        "discord_delete_message"
+    }
+
+    fn description(&self) -> &'static str {
+        "delete a message, messages by others only if the user asking may delete them"
+    }
+
+    fn parameters(&self) -> Schema {
+        DeleteMessageArgs::schema()
+    }
+
+    fn permissions(&self) -> Permissions {
+        Permissions::VIEW_CHANNEL
+    }
+
+    fn call<'a>(
+        &'a self,
+        state: &'a State,
+        turn: &'a Turn,
+        args: Struct,
+    ) -> BoxFuture<'a, anyhow::Result<Struct>> {
+        Box::pin(async move {
+            let DeleteMessageArgs {
+                channel_id,
+                message_id,
+            } = value::from_struct(args)?;
+
+            info!("discord_delete_message(channel_id={channel_id}, message_id={message_id})");
+
+            policy::check_target(
+                state,
+                turn,
+                Some(&state.options.tools.delete_message),
+                channel_id,
+                message_id,
+            )
+            .await?;
+
+            state.rest.delete_message(channel_id, message_id).await?;
+
+            Ok(output(format!(
+                "successfully deleted message channel_id={channel_id} message_id={message_id}"
+            )))
+        })
+    }
+}
@@ -0,0 +1,87 @@
+use super::{Tool, join, output, policy};
+use crate::State;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Schema;
+use crate::schema::ToSchema;
+use crate::session::Turn;
+use crate::{reply, schema, value};
+use futures_util::future::BoxFuture;
+use prost_types::Struct;
+use serde::Deserialize;
+use tracing::info;
+use twilight_model::guild::Permissions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, MessageMarker};
+
+pub struct EditMessage;
+
+schema! {
+    #[derive(Deserialize)]
+    struct EditMessageArgs {
+        /// the channel the message is in
+        channel_id: Id<ChannelMarker>,
+        /// the message to edit
+        message_id: Id<MessageMarker>,
+        /// the new message content
+        new_content: String,
+    }
+}
+
+impl Tool for EditMessage {
+    fn name(&self) -> &'static str {
+        "discord_edit_message"
+    }
+
+    fn description(&self) -> &'static str {
+        "edit one of your own messages"
+    }
+
+    fn parameters(&self) -> Schema {
+        EditMessageArgs::schema()
+    }
+
+    fn permissions(&self) -> Permissions {
+        Permissions::VIEW_CHANNEL
+    }
+
+    fn call<'a>(
+        &'a self,
+        state: &'a State,
+        turn: &'a Turn,
+        args: Struct,
+    ) -> BoxFuture<'a, anyhow::Result<Struct>> {
+        Box::pin(async move {
+            let EditMessageArgs {
+                channel_id,
+                message_id,
+                new_content,
+            } = value::from_struct(args)?;
+
+            info!(
+                "discord_edit_message(channel_id={channel_id}, message_id={message_id}, new_content={new_content})"
+            );
+
+            // only the author can edit a message, so no policy allows anything else
+            policy::check_target(state, turn, None, channel_id, message_id).await?;
+
+            let message_ids = reply::edit(
+                state,
+                channel_id,
+                message_id,
+                &new_content,
+                &turn.allowed_mentions(state),
+            )
+            .await?;
+
+            if message_ids.is_empty() {
+                return Ok(output(format!(
+                    "successfully edited channel_id={channel_id} message_id={message_id}"
+                )));
+            }
+
+            Ok(output(format!(
+                "successfully edited channel_id={channel_id} message_id={message_id}, the rest of the content did not fit and was sent as message_ids={}",
+                join(&message_ids),
+            )))
+        })
+    }
+}
@@ -0,0 +1,86 @@
+use super::{Tool, ToolError, output};
+use crate::State;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Schema;
+use crate::schema::ToSchema;
+use crate::session::Turn;
+use crate::{schema, value};
+use futures_util::future::BoxFuture;
+use prost_types::Struct;
+use serde::Deserialize;
+use tracing::info;
+use twilight_model::channel::ChannelType;
+use twilight_model::guild::Permissions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::ChannelMarker;
+
+pub struct JoinVoice;
+
+schema! {
+    #[derive(Deserialize)]
+    struct JoinVoiceArgs {
+        /// the voice channel to join
+        channel_id: Id<ChannelMarker>,
+    }
+}
+
+impl Tool for JoinVoice {
+    fn name(&self) -> &'static str {
+        "discord_join_voice"
+    }
+
+    fn description(&self) -> &'static str {
+        "join a voice channel to talk with its members, leaving any other voice channel in the guild"
+    }
+
+    fn parameters(&self) -> Schema {
+        JoinVoiceArgs::schema()
+    }
+
+    fn permissions(&self) -> Permissions {
+        Permissions::VIEW_CHANNEL
+            .union(Permissions::CONNECT)
+            .union(Permissions::SPEAK)
+    }
+
+    fn call<'a>(
+        &'a self,
+        state: &'a State,
+        _turn: &'a Turn,
+        args: Struct,
+    ) -> BoxFuture<'a, anyhow::Result<Struct>> {
+        Box::pin(async move {
+            let JoinVoiceArgs { channel_id } = value::from_struct(args)?;
+
+            info!("discord_join_voice(channel_id={channel_id})");
+
+            let Some(voice) = &state.voice else {
+                anyhow::bail!("voice is disabled");
+            };
+
+            let guild_id = state
+                .cache
+                .channel(channel_id)
+                .filter(|channel| {
+                    matches!(
+                        channel.kind,
+                        ChannelType::GuildVoice | ChannelType::GuildStageVoice
+                    )
+                })
+                .and_then(|channel| channel.guild_id);
+
+            let Some(guild_id) = guild_id else {
+                return Err(ToolError::InvalidArgument {
+                    field: String::from("channel_id"),
+                    message: format!("channel_id={channel_id} is not a known voice channel"),
+                }
+                .into());
+            };
+
+            voice.join(state, guild_id, channel_id).await?;
+
+            Ok(output(format!(
+                "successfully joined voice channel_id={channel_id}, a separate voice session talks with its members"
+            )))
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use super::{Tool, output};
+use crate::State;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Schema;
+use crate::schema::ToSchema;
+use crate::session::Turn;
+use crate::{schema, value};
+use futures_util::future::BoxFuture;
+use prost_types::Struct;
+use serde::Deserialize;
+use tracing::info;
+use twilight_model::id::Id;
+use twilight_model::id::marker::GuildMarker;
+
+pub struct LeaveVoice;
+
+schema! {
+    #[derive(Deserialize)]
+    struct LeaveVoiceArgs {
+        /// the guild whose voice channel to leave
+        guild_id: Id<GuildMarker>,
+    }
+}
+
+impl Tool for LeaveVoice {
+    fn name(&self) -> &'static str {
+        "discord_leave_voice"
+    }
+
+    fn description(&self) -> &'static str {
+        "leave the voice channel you are in within a guild"
+    }
+
+    fn parameters(&self) -> Schema {
+        LeaveVoiceArgs::schema()
+    }
+
+    fn call<'a>(
+        &'a self,
+        state: &'a State,
+        _turn: &'a Turn,
+        args: Struct,
+    ) -> BoxFuture<'a, anyhow::Result<Struct>> {
+        Box::pin(async move {
+            let LeaveVoiceArgs { guild_id } = value::from_struct(args)?;
+
+            info!("discord_leave_voice(guild_id={guild_id})");
+
+            let Some(voice) = &state.voice else {
+                anyhow::bail!("voice is disabled");
+            };
+
+            let Some(channel_id) = voice.channel_id(guild_id) else {
+                anyhow::bail!("you are not in a voice channel in guild_id={guild_id}");
+            };
+
+            voice.leave(guild_id).await?;
+
+            Ok(output(format!(
+                "successfully left voice channel_id={channel_id}"
+            )))
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use super::ToolError;
+use crate::State;
+use crate::session::Turn;
+use serde::Serialize;
+use tracing::warn;
+use twilight_model::guild::Permissions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, UserMarker};
+
+/// Whose permissions were insufficient.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subject {
+    /// ari itself.
+    You,
+    /// One of the users who triggered the turn.
+    User,
+}
+
+/// Check that ari, and optionally every user who triggered `turn`, has `required` in `channel_id`.
+///
+/// Permissions that cannot be calculated from the cache, such as in direct messages or for
+/// members that were never cached, are left for Discord to enforce.
+pub fn check(
+    state: &State,
+    turn: &Turn,
+    channel_id: Id<ChannelMarker>,
+    required: Permissions,
+) -> Result<(), ToolError> {
+    if required.is_empty() {
+        return Ok(());
+    }
+
+    let Some((guild_id, is_thread)) = state
+        .cache
+        .channel(channel_id)
+        .map(|channel| (channel.guild_id, channel.kind.is_thread()))
+    else {
+        return Ok(());
+    };
+
+    if guild_id.is_none() {
+        return Ok(());
+    }
+
+    // threads have their own permission for sending messages
+    let required = if is_thread && required.contains(Permissions::SEND_MESSAGES) {
+        required
+            .difference(Permissions::SEND_MESSAGES)
+            .union(Permissions::SEND_MESSAGES_IN_THREADS)
+    } else {
+        required
+    };
+
+    if let Some(current_user) = state.cache.current_user() {
+        check_user(state, Subject::You, current_user.id, channel_id, required)?;
+    }
+
+    if state.options.tools.check_user_permissions {
+        for author_id in &turn.author_ids {
+            check_user(state, Subject::User, *author_id, channel_id, required)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that `user_id` has `required` in `channel_id`.
+pub fn check_user(
+    state: &State,
+    subject: Subject,
+    user_id: Id<UserMarker>,
+    channel_id: Id<ChannelMarker>,
+    required: Permissions,
+) -> Result<(), ToolError> {
+    let permissions = match state.cache.permissions().in_channel(user_id, channel_id) {
+        Ok(permissions) => permissions,
+        Err(error) => {
+            warn!(
+                "failed to calculate permissions of user_id={user_id} in channel_id={channel_id}: {error}"
+            );
+
+            return Ok(());
+        }
+    };
+
+    let missing = required.difference(permissions);
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    Err(ToolError::MissingPermissions {
+        subject,
+        permissions: missing
+            .iter_names()
+            .map(|(name, _permission)| name.to_string())
+            .collect(),
+        channel_id,
+    })
+}
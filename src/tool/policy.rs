@@ -0,0 +1,130 @@
+use super::ToolError;
+use super::permissions::{self, Subject};
+use crate::State;
+use crate::options::TargetPolicy;
+use crate::session::Turn;
+use anyhow::Context;
+use twilight_model::guild::Permissions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, MessageMarker, UserMarker};
+
+/// Fail unless the model may act on `message_id` during `turn`.
+///
+/// ari's own messages are always fine. Messages by others require `policy` to trust every user who
+/// triggered the turn and ari to have Manage Messages, without a policy they are never fine.
+pub async fn check_target(
+    state: &State,
+    turn: &Turn,
+    policy: Option<&TargetPolicy>,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> anyhow::Result<()> {
+    let current_user_id = state
+        .cache
+        .current_user()
+        .map(|user| user.id)
+        .context("current user is not known yet")?;
+
+    if author(state, channel_id, message_id).await? == current_user_id {
+        return Ok(());
+    }
+
+    let Some(policy) = policy else {
+        return Err(ToolError::Forbidden {
+            reason: format!("message_id={message_id} is not your own message"),
+        }
+        .into());
+    };
+
+    if !trusts(state, turn, policy, channel_id) {
+        return Err(ToolError::Forbidden {
+            reason: format!(
+                "message_id={message_id} is not your own message, and not everyone who asked may have you act on messages by others"
+            ),
+        }
+        .into());
+    }
+
+    permissions::check_user(
+        state,
+        Subject::You,
+        current_user_id,
+        channel_id,
+        Permissions::MANAGE_MESSAGES,
+    )?;
+
+    Ok(())
+}
+
+/// Who wrote `message_id`, from the cache if possible.
+async fn author(
+    state: &State,
+    channel_id: Id<ChannelMarker>,
+    message_id: Id<MessageMarker>,
+) -> anyhow::Result<Id<UserMarker>> {
+    if let Some(message) = state.cache.message(message_id) {
+        return Ok(message.author());
+    }
+
+    let message = state
+        .rest
+        .message(channel_id, message_id)
+        .await?
+        .model()
+        .await?;
+
+    Ok(message.author.id)
+}
+
+/// Whether `policy` trusts all users who triggered `turn` in `channel_id`.
+///
+/// Inputs queued while the model was busy share a turn, so trusting any one of their authors
+/// would let the others act through them.
+fn trusts(
+    state: &State,
+    turn: &Turn,
+    policy: &TargetPolicy,
+    channel_id: Id<ChannelMarker>,
+) -> bool {
+    !turn.author_ids.is_empty()
+        && turn
+            .author_ids
+            .iter()
+            .all(|author_id| trusts_user(state, *author_id, policy, channel_id))
+}
+
+/// Whether `policy` trusts `author_id` in `channel_id`.
+fn trusts_user(
+    state: &State,
+    author_id: Id<UserMarker>,
+    policy: &TargetPolicy,
+    channel_id: Id<ChannelMarker>,
+) -> bool {
+    if policy.manage_messages
+        && state
+            .cache
+            .permissions()
+            .in_channel(author_id, channel_id)
+            .is_ok_and(|permissions| permissions.contains(Permissions::MANAGE_MESSAGES))
+    {
+        return true;
+    }
+
+    let Some(guild_id) = state
+        .cache
+        .channel(channel_id)
+        .and_then(|channel| channel.guild_id)
+    else {
+        return false;
+    };
+
+    state
+        .cache
+        .member(guild_id, author_id)
+        .is_some_and(|member| {
+            member
+                .roles()
+                .iter()
+                .any(|role_id| policy.roles.contains(role_id))
+        })
+}
@@ -0,0 +1,80 @@
+use super::{Tool, output};
+use crate::State;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Schema;
+use crate::schema::ToSchema;
+use crate::session::Turn;
+use crate::{schema, value};
+use futures_util::future::BoxFuture;
+use prost_types::Struct;
+use serde::Deserialize;
+use tracing::info;
+use twilight_http::request::channel::reaction::RequestReactionType;
+use twilight_model::guild::Permissions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, MessageMarker};
+
+pub struct ReactToMessage;
+
+schema! {
+    #[derive(Deserialize)]
+    struct ReactToMessageArgs {
+        /// the channel the message is in
+        channel_id: Id<ChannelMarker>,
+        /// the message to react to
+        message_id: Id<MessageMarker>,
+        /// a single unicode emoji
+        emoji: String,
+    }
+}
+
+impl Tool for ReactToMessage {
+    fn name(&self) -> &'static str {
+        "discord_react_to_message"
+    }
+
+    fn description(&self) -> &'static str {
+        "react to a message with a single unicode emoji"
+    }
+
+    fn parameters(&self) -> Schema {
+        ReactToMessageArgs::schema()
+    }
+
+    fn permissions(&self) -> Permissions {
+        Permissions::VIEW_CHANNEL
+            .union(Permissions::READ_MESSAGE_HISTORY)
+            .union(Permissions::ADD_REACTIONS)
+    }
+
+    fn call<'a>(
+        &'a self,
+        state: &'a State,
+        _turn: &'a Turn,
+        args: Struct,
+    ) -> BoxFuture<'a, anyhow::Result<Struct>> {
+        Box::pin(async move {
+            let ReactToMessageArgs {
+                channel_id,
+                message_id,
+                emoji,
+            } = value::from_struct(args)?;
+
+            info!(
+                "discord_react_to_message(channel_id={channel_id}, message_id={message_id}, emoji={emoji})"
+            );
+
+            state
+                .rest
+                .create_reaction(
+                    channel_id,
+                    message_id,
+                    &RequestReactionType::Unicode { name: &emoji },
+                )
+                .await?;
+
+            Ok(output(format!(
+                "successfully reacted to channel_id={channel_id} message_id={message_id}"
+            )))
+        })
+    }
+}
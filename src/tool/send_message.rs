@@ -0,0 +1,72 @@
+use super::{Tool, join, output};
+use crate::State;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::Schema;
+use crate::schema::ToSchema;
+use crate::session::Turn;
+use crate::{reply, schema, value};
+use futures_util::future::BoxFuture;
+use prost_types::Struct;
+use serde::Deserialize;
+use tracing::info;
+use twilight_model::guild::Permissions;
+use twilight_model::id::Id;
+use twilight_model::id::marker::ChannelMarker;
+
+pub struct SendMessage;
+
+schema! {
+    #[derive(Deserialize)]
+    struct SendMessageArgs {
+        /// the channel to send the message in
+        channel_id: Id<ChannelMarker>,
+        /// the message content
+        content: String,
+    }
+}
+
+impl Tool for SendMessage {
+    fn name(&self) -> &'static str {
+        "discord_send_message"
+    }
+
+    fn description(&self) -> &'static str {
+        "send a message in a channel"
+    }
+
+    fn parameters(&self) -> Schema {
+        SendMessageArgs::schema()
+    }
+
+    fn permissions(&self) -> Permissions {
+        Permissions::VIEW_CHANNEL.union(Permissions::SEND_MESSAGES)
+    }
+
+    fn call<'a>(
+        &'a self,
+        state: &'a State,
+        turn: &'a Turn,
+        args: Struct,
+    ) -> BoxFuture<'a, anyhow::Result<Struct>> {
+        Box::pin(async move {
+            let SendMessageArgs {
+                channel_id,
+                content,
+            } = value::from_struct(args)?;
+
+            info!("discord_send_message(channel_id={channel_id}, content={content:?})");
+
+            let message_ids =
+                reply::send(state, channel_id, &content, &turn.allowed_mentions(state)).await?;
+
+            Ok(output(match &message_ids[..] {
+                [message_id] => format!(
+                    "successfully sent your message to channel_id={channel_id} message_id={message_id}"
+                ),
+                message_ids => format!(
+                    "successfully sent your message to channel_id={channel_id}, split into message_ids={}",
+                    join(message_ids),
+                ),
+            }))
+        })
+    }
+}
@@ -0,0 +1,299 @@
+//! Deciding which messages the model responds to.
+
+use crate::State;
+use rand::Rng;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use tracing::info;
+use twilight_model::channel::Message;
+use twilight_model::channel::message::MessageType;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, UserMarker};
+
+/// What to do with a message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Trigger {
+    /// Send it to the model and have it respond.
+    Respond,
+    /// Add it to the context without having the model respond.
+    Ambient,
+    Ignore,
+}
+
+/// When messages trigger a response, the first matching rule wins.
+///
+/// Messages by bots, webhooks and the system are ignored entirely unless enabled.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TriggerOptions {
+    /// Accept messages by any bot.
+    #[serde(default)]
+    pub bots: bool,
+    /// Bots whose messages are accepted, ids are quoted strings.
+    #[serde(default)]
+    pub allowed_bots: Vec<Id<UserMarker>>,
+    /// Accept messages sent through webhooks.
+    #[serde(default)]
+    pub webhooks: bool,
+    /// Accept system messages such as joins, pins and boosts.
+    #[serde(default)]
+    pub system: bool,
+    /// Stop responding to bots after this many consecutive responses to them in a channel, until
+    /// someone else speaks. 0 never stops.
+    #[serde(default = "default_max_bot_exchanges")]
+    pub max_bot_exchanges: u32,
+    /// Respond to every message.
+    #[serde(default)]
+    pub always: bool,
+    /// Respond to direct messages.
+    #[serde(default = "default_true")]
+    pub dm: bool,
+    /// Respond to messages mentioning ari or its role.
+    #[serde(default = "default_true")]
+    pub mention: bool,
+    /// Respond to replies to ari's messages.
+    #[serde(default = "default_true")]
+    pub reply: bool,
+    /// Respond to every message in these channels and their threads, ids are quoted strings.
+    #[serde(default)]
+    pub channels: Vec<Id<ChannelMarker>>,
+    /// Respond to messages containing any of these words, ignoring case.
+    #[serde(default, deserialize_with = "deserialize_keywords")]
+    pub keywords: Option<Regex>,
+    /// Respond to messages matching any of these regular expressions.
+    #[serde(default, deserialize_with = "deserialize_patterns")]
+    pub patterns: Vec<Regex>,
+    /// Probability of responding to any other message, between 0 and 1.
+    #[serde(default)]
+    pub chance: f64,
+    /// Add messages that trigger no response to the context anyway.
+    #[serde(default)]
+    pub ambient: bool,
+}
+
+impl Default for TriggerOptions {
+    fn default() -> Self {
+        Self {
+            bots: false,
+            allowed_bots: Vec::new(),
+            webhooks: false,
+            system: false,
+            max_bot_exchanges: default_max_bot_exchanges(),
+            always: false,
+            dm: true,
+            mention: true,
+            reply: true,
+            channels: Vec::new(),
+            keywords: None,
+            patterns: Vec::new(),
+            chance: 0.0,
+            ambient: false,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_bot_exchanges() -> u32 {
+    3
+}
+
+fn deserialize_keywords<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Regex>, D::Error> {
+    use serde::de::Error;
+
+    let keywords = Vec::<String>::deserialize(deserializer)?;
+
+    if keywords.is_empty() {
+        return Ok(None);
+    }
+
+    let alternatives = keywords
+        .iter()
+        .map(|keyword| regex::escape(keyword.trim()))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    Regex::new(&format!(r"(?i)\b(?:{alternatives})\b"))
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+fn deserialize_patterns<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Regex>, D::Error> {
+    use serde::de::Error;
+
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern)
+                .map_err(|error| D::Error::custom(format!("invalid pattern `{pattern}`: {error}")))
+        })
+        .collect()
+}
+
+impl TriggerOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&self.chance) {
+            anyhow::bail!(
+                "trigger.chance must be between 0 and 1, got {}",
+                self.chance
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Decide what to do with `message`, which was not sent by ari.
+    pub fn evaluate(
+        &self,
+        state: &State,
+        current_user_id: Id<UserMarker>,
+        message: &Message,
+    ) -> Trigger {
+        if !self.accepts(message) {
+            return Trigger::Ignore;
+        }
+
+        let respond = self.always
+            || (self.dm && message.guild_id.is_none())
+            || (self.mention && mentions(state, current_user_id, message))
+            || (self.reply
+                && message
+                    .referenced_message
+                    .as_ref()
+                    .is_some_and(|referenced| referenced.author.id == current_user_id))
+            || self.in_channels(state, message.channel_id)
+            || self
+                .keywords
+                .as_ref()
+                .is_some_and(|keywords| keywords.is_match(&message.content))
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| pattern.is_match(&message.content))
+            || (self.chance > 0.0 && rand::rng().random_bool(self.chance));
+
+        if respond {
+            Trigger::Respond
+        } else {
+            self.fallback()
+        }
+    }
+
+    /// What happens to accepted messages that trigger no response.
+    fn fallback(&self) -> Trigger {
+        if self.ambient {
+            Trigger::Ambient
+        } else {
+            Trigger::Ignore
+        }
+    }
+
+    /// Whether the author and kind of `message` are accepted at all.
+    pub fn accepts(&self, message: &Message) -> bool {
+        if !matches!(message.kind, MessageType::Regular | MessageType::Reply) && !self.system {
+            return false;
+        }
+
+        if message.webhook_id.is_some() {
+            return self.webhooks;
+        }
+
+        !message.author.bot || self.accepts_bot(message.author.id)
+    }
+
+    /// Whether the bot `user_id` is accepted.
+    pub fn accepts_bot(&self, user_id: Id<UserMarker>) -> bool {
+        self.bots || self.allowed_bots.contains(&user_id)
+    }
+
+    fn in_channels(&self, state: &State, channel_id: Id<ChannelMarker>) -> bool {
+        if self.channels.contains(&channel_id) {
+            return true;
+        }
+
+        state
+            .cache
+            .channel(channel_id)
+            .and_then(|channel| channel.parent_id)
+            .is_some_and(|parent_id| self.channels.contains(&parent_id))
+    }
+}
+
+/// Whether `message` mentions ari, directly or through its managed role.
+fn mentions(state: &State, current_user_id: Id<UserMarker>, message: &Message) -> bool {
+    if message
+        .mentions
+        .iter()
+        .any(|mention| mention.id == current_user_id)
+    {
+        return true;
+    }
+
+    let Some(guild_id) = message.guild_id else {
+        return false;
+    };
+
+    let Some(member) = state.cache.member(guild_id, current_user_id) else {
+        return false;
+    };
+
+    message.mention_roles.iter().any(|role_id| {
+        member.roles().contains(role_id)
+            && state.cache.role(*role_id).is_some_and(|role| role.managed)
+    })
+}
+
+/// Stops bots from keeping ari in a conversation with them forever.
+#[derive(Default)]
+pub struct LoopDetector {
+    /// Consecutive responses to bots in each channel.
+    exchanges: HashMap<Id<ChannelMarker>, u32>,
+}
+
+impl LoopDetector {
+    /// Pass on `trigger` for `message`, unless it continues a conversation between bots that
+    /// went on for too long.
+    pub fn check(
+        &mut self,
+        options: &TriggerOptions,
+        message: &Message,
+        trigger: Trigger,
+    ) -> Trigger {
+        // webhooks are usually bridges relaying people, they count as bots nonetheless
+        if !message.author.bot && message.webhook_id.is_none() {
+            self.exchanges.remove(&message.channel_id);
+
+            return trigger;
+        }
+
+        if trigger != Trigger::Respond || options.max_bot_exchanges == 0 {
+            return trigger;
+        }
+
+        let exchanges = self.exchanges.entry(message.channel_id).or_default();
+
+        if *exchanges >= options.max_bot_exchanges {
+            // counting past the limit keeps this from being logged for every message
+            if *exchanges == options.max_bot_exchanges {
+                info!(
+                    "stop responding to bots in channel_id={} after {exchanges} exchanges",
+                    message.channel_id
+                );
+
+                *exchanges += 1;
+            }
+
+            return options.fallback();
+        }
+
+        *exchanges += 1;
+
+        trigger
+    }
+}
@@ -0,0 +1,137 @@
+//! Serde support for `prost_types::Struct` and `prost_types::Value`.
+//!
+//! Function call arguments and function responses are both protobuf `Struct`s, this lets tools
+//! work with plain Rust types instead.
+
+use prost_types::{Struct, Value};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+pub use self::de::Deserializer;
+pub use self::ser::Serializer;
+
+mod de;
+mod ser;
+
+/// Deserialize `T` from a `Struct`.
+pub fn from_struct<T: DeserializeOwned>(value: Struct) -> Result<T, Error> {
+    T::deserialize(Deserializer::from_struct(value))
+}
+
+/// Deserialize `T` from a `Value`.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(value))
+}
+
+/// Serialize `value` into a `Struct`.
+///
+/// Fails if `value` does not serialize to a map or struct.
+pub fn to_struct<T: Serialize + ?Sized>(value: &T) -> Result<Struct, Error> {
+    use prost_types::value::Kind;
+
+    match to_value(value)?.kind {
+        Some(Kind::StructValue(value)) => Ok(value),
+        _ => Err(Error::custom("expected a map or struct")),
+    }
+}
+
+/// Serialize `value` into a `Value`.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, Error> {
+    value.serialize(Serializer)
+}
+
+/// What went wrong while converting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A required field was not present.
+    MissingField(&'static str),
+    /// A field was present that the target type does not know about.
+    UnknownField(String),
+    /// Anything else, such as a value of the wrong type.
+    Custom(String),
+}
+
+/// An error converting to or from a `Struct`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    path: Vec<String>,
+    kind: ErrorKind,
+}
+
+impl Error {
+    fn new(kind: ErrorKind) -> Self {
+        Self {
+            path: Vec::new(),
+            kind,
+        }
+    }
+
+    fn custom(message: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Custom(message.to_string()))
+    }
+
+    /// Record that this error happened within `segment`.
+    fn within(mut self, segment: impl fmt::Display) -> Self {
+        self.path.insert(0, segment.to_string());
+        self
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// Dotted path to the value the error occurred at, empty for the root.
+    pub fn path(&self) -> String {
+        self.path.join(".")
+    }
+
+    /// Dotted path to the offending field, including the field itself.
+    pub fn field(&self) -> String {
+        let mut path = self.path.clone();
+
+        match &self.kind {
+            ErrorKind::MissingField(field) => path.push(field.to_string()),
+            ErrorKind::UnknownField(field) => path.push(field.clone()),
+            ErrorKind::Custom(_) => {}
+        }
+
+        path.join(".")
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(fmt, "{}: ", self.path())?;
+        }
+
+        match &self.kind {
+            ErrorKind::MissingField(field) => write!(fmt, "missing field `{field}`"),
+            ErrorKind::UnknownField(field) => write!(fmt, "unknown field `{field}`"),
+            ErrorKind::Custom(message) => fmt.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl serde::de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self::custom(message)
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self::new(ErrorKind::MissingField(field))
+    }
+
+    fn unknown_field(field: &str, _expected: &'static [&'static str]) -> Self {
+        Self::new(ErrorKind::UnknownField(field.to_string()))
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self::custom(message)
+    }
+}
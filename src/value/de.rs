@@ -0,0 +1,491 @@
+use super::Error;
+use prost_types::value::Kind;
+use prost_types::{ListValue, Struct, Value};
+use serde::de::{self, IntoDeserializer, Unexpected, Visitor};
+use serde::forward_to_deserialize_any;
+use std::collections::btree_map;
+use std::vec;
+
+/// Deserializes Rust types from a `Value`.
+pub struct Deserializer {
+    value: Value,
+}
+
+impl Deserializer {
+    pub fn new(value: Value) -> Self {
+        Self { value }
+    }
+
+    pub fn from_struct(value: Struct) -> Self {
+        Self::new(Value {
+            kind: Some(Kind::StructValue(value)),
+        })
+    }
+
+    fn unexpected(&self) -> Unexpected<'_> {
+        match &self.value.kind {
+            None | Some(Kind::NullValue(_)) => Unexpected::Unit,
+            Some(Kind::NumberValue(number)) => Unexpected::Float(*number),
+            Some(Kind::StringValue(string)) => Unexpected::Str(string),
+            Some(Kind::BoolValue(bool)) => Unexpected::Bool(*bool),
+            Some(Kind::StructValue(_)) => Unexpected::Map,
+            Some(Kind::ListValue(_)) => Unexpected::Seq,
+        }
+    }
+
+    fn invalid_type(&self, expected: &dyn de::Expected) -> Error {
+        de::Error::invalid_type(self.unexpected(), expected)
+    }
+}
+
+/// Deserialize an integer, accepting integral numbers and numeric strings.
+///
+/// Models frequently quote numbers, so both forms are accepted.
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident: $ty:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let integer = match &self.value.kind {
+                    Some(Kind::NumberValue(number)) if number.fract() == 0.0 => {
+                        <$ty>::try_from(*number as i128).ok()
+                    }
+                    Some(Kind::StringValue(string)) => string.trim().parse::<$ty>().ok(),
+                    _ => None,
+                };
+
+                match integer {
+                    Some(integer) => visitor.$visit(integer),
+                    None => Err(self.invalid_type(&visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.kind {
+            None | Some(Kind::NullValue(_)) => visitor.visit_unit(),
+            Some(Kind::NumberValue(number)) => visit_number(number, visitor),
+            Some(Kind::StringValue(string)) => visitor.visit_string(string),
+            Some(Kind::BoolValue(bool)) => visitor.visit_bool(bool),
+            Some(Kind::StructValue(value)) => visitor.visit_map(MapAccess::new(value)),
+            Some(Kind::ListValue(value)) => visitor.visit_seq(SeqAccess::new(value)),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.value.kind {
+            Some(Kind::NumberValue(number)) => visitor.visit_f64(*number),
+            Some(Kind::StringValue(string)) => match string.trim().parse() {
+                Ok(number) => visitor.visit_f64(number),
+                Err(_error) => Err(self.invalid_type(&visitor)),
+            },
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.value.kind {
+            Some(Kind::BoolValue(bool)) => visitor.visit_bool(*bool),
+            Some(Kind::StringValue(string)) if string == "true" => visitor.visit_bool(true),
+            Some(Kind::StringValue(string)) if string == "false" => visitor.visit_bool(false),
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.kind {
+            None | Some(Kind::NullValue(_)) => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.value.kind {
+            Some(Kind::StringValue(variant)) => visitor.visit_enum(variant.into_deserializer()),
+            Some(Kind::StructValue(value)) if value.fields.len() == 1 => {
+                let (variant, value) = value.fields.into_iter().next().unwrap();
+
+                visitor.visit_enum(EnumAccess { variant, value })
+            }
+            _ => Err(self.invalid_type(&"a string or an object with a single key")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+fn visit_number<'de, V: Visitor<'de>>(number: f64, visitor: V) -> Result<V::Value, Error> {
+    if number.fract() == 0.0 {
+        if (0.0..=u64::MAX as f64).contains(&number) {
+            return visitor.visit_u64(number as u64);
+        }
+
+        if (i64::MIN as f64..0.0).contains(&number) {
+            return visitor.visit_i64(number as i64);
+        }
+    }
+
+    visitor.visit_f64(number)
+}
+
+struct MapAccess {
+    fields: btree_map::IntoIter<String, Value>,
+    value: Option<(String, Value)>,
+}
+
+impl MapAccess {
+    fn new(value: Struct) -> Self {
+        Self {
+            fields: value.fields.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.fields.next() else {
+            return Ok(None);
+        };
+
+        let result = seed.deserialize(key.as_str().into_deserializer());
+
+        self.value = Some((key, value));
+
+        result.map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| Error::custom("value requested before key"))?;
+
+        seed.deserialize(Deserializer::new(value))
+            .map_err(|error| error.within(key))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+struct SeqAccess {
+    values: vec::IntoIter<Value>,
+    index: usize,
+}
+
+impl SeqAccess {
+    fn new(value: ListValue) -> Self {
+        Self {
+            values: value.values.into_iter(),
+            index: 0,
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some(value) = self.values.next() else {
+            return Ok(None);
+        };
+
+        let index = self.index;
+
+        self.index += 1;
+
+        seed.deserialize(Deserializer::new(value))
+            .map(Some)
+            .map_err(|error| error.within(index))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct EnumAccess {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess {
+    type Error = Error;
+    type Variant = VariantAccess;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantAccess), Error> {
+        let variant = seed.deserialize(self.variant.as_str().into_deserializer())?;
+
+        Ok((
+            variant,
+            VariantAccess {
+                variant: self.variant,
+                value: self.value,
+            },
+        ))
+    }
+}
+
+struct VariantAccess {
+    variant: String,
+    value: Value,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value.kind {
+            None | Some(Kind::NullValue(_)) => Ok(()),
+            _ => Err(Deserializer::new(self.value).invalid_type(&"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(Deserializer::new(self.value))
+            .map_err(|error| error.within(self.variant))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(Deserializer::new(self.value), visitor)
+            .map_err(|error| error.within(self.variant))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(Deserializer::new(self.value), visitor)
+            .map_err(|error| error.within(self.variant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::{self, ErrorKind};
+    use prost_types::value::Kind;
+    use prost_types::{ListValue, Struct, Value};
+    use serde::Deserialize;
+    use twilight_model::id::Id;
+    use twilight_model::id::marker::ChannelMarker;
+
+    fn string(string: &str) -> Value {
+        Value {
+            kind: Some(Kind::StringValue(string.to_string())),
+        }
+    }
+
+    fn number(number: f64) -> Value {
+        Value {
+            kind: Some(Kind::NumberValue(number)),
+        }
+    }
+
+    fn list(values: Vec<Value>) -> Value {
+        Value {
+            kind: Some(Kind::ListValue(ListValue { values })),
+        }
+    }
+
+    fn object<const N: usize>(fields: [(&str, Value); N]) -> Value {
+        let fields = fields
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+
+        Value {
+            kind: Some(Kind::StructValue(Struct { fields })),
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct Outer {
+        inner: Inner,
+        #[serde(default)]
+        items: Vec<Inner>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct Inner {
+        count: u32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Shape {
+        Dot,
+        Label(String),
+        Circle { radius: f64 },
+        Line(u32, u32),
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(value::from_value::<u32>(number(42.0)), Ok(42));
+        assert_eq!(value::from_value::<i64>(number(-3.0)), Ok(-3));
+        assert!(value::from_value::<u32>(number(1.5)).is_err());
+        assert!(value::from_value::<u8>(number(256.0)).is_err());
+        assert!(value::from_value::<u32>(number(-1.0)).is_err());
+    }
+
+    #[test]
+    fn quoted_integers() {
+        assert_eq!(value::from_value::<u32>(string("42")), Ok(42));
+        assert_eq!(value::from_value::<i16>(string(" -7 ")), Ok(-7));
+        assert_eq!(
+            value::from_value::<u64>(string("18446744073709551615")),
+            Ok(u64::MAX)
+        );
+        assert!(value::from_value::<u32>(string("forty two")).is_err());
+        assert_eq!(value::from_value::<f64>(string("2.5")), Ok(2.5));
+        assert_eq!(value::from_value::<bool>(string("true")), Ok(true));
+    }
+
+    #[test]
+    fn id() {
+        let id = Id::<ChannelMarker>::new(1_234_567_890_123_456_789);
+
+        assert_eq!(value::from_value(string("1234567890123456789")), Ok(id));
+        assert_eq!(
+            value::from_value::<Id<ChannelMarker>>(number(42.0)),
+            Ok(Id::new(42))
+        );
+        assert!(value::from_value::<Id<ChannelMarker>>(string("0")).is_err());
+    }
+
+    #[test]
+    fn nested_error_field() {
+        let error =
+            value::from_value::<Outer>(object([("inner", object([("count", string("many"))]))]))
+                .unwrap_err();
+
+        assert_eq!(error.field(), "inner.count");
+        assert!(matches!(error.kind(), ErrorKind::Custom(_)));
+
+        let error = value::from_value::<Outer>(object([
+            ("inner", object([("count", number(1.0))])),
+            (
+                "items",
+                list(vec![
+                    object([("count", number(1.0))]),
+                    object([("count", number(-1.0))]),
+                ]),
+            ),
+        ]))
+        .unwrap_err();
+
+        assert_eq!(error.field(), "items.1.count");
+    }
+
+    #[test]
+    fn missing_field() {
+        let error = value::from_value::<Outer>(object([("inner", object([]))])).unwrap_err();
+
+        assert_eq!(error.kind(), &ErrorKind::MissingField("count"));
+        assert_eq!(error.path(), "inner");
+        assert_eq!(error.field(), "inner.count");
+
+        let error = value::from_value::<Outer>(object([])).unwrap_err();
+
+        assert_eq!(error.field(), "inner");
+    }
+
+    #[test]
+    fn unknown_field() {
+        let error = value::from_value::<Outer>(object([(
+            "inner",
+            object([("count", number(1.0)), ("extra", number(2.0))]),
+        )]))
+        .unwrap_err();
+
+        assert_eq!(
+            error.kind(),
+            &ErrorKind::UnknownField(String::from("extra"))
+        );
+        assert_eq!(error.field(), "inner.extra");
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(value::from_value(string("dot")), Ok(Shape::Dot));
+        assert_eq!(
+            value::from_value(object([("dot", Value::default())])),
+            Ok(Shape::Dot)
+        );
+        assert_eq!(
+            value::from_value(object([("label", string("hi"))])),
+            Ok(Shape::Label(String::from("hi")))
+        );
+        assert_eq!(
+            value::from_value(object([("circle", object([("radius", number(2.0))]))])),
+            Ok(Shape::Circle { radius: 2.0 })
+        );
+        assert_eq!(
+            value::from_value(object([("line", list(vec![number(1.0), string("2")]))])),
+            Ok(Shape::Line(1, 2))
+        );
+
+        let error =
+            value::from_value::<Shape>(object([("circle", object([("radius", string("wide"))]))]))
+                .unwrap_err();
+
+        assert_eq!(error.field(), "circle.radius");
+
+        assert!(value::from_value::<Shape>(string("square")).is_err());
+        assert!(
+            value::from_value::<Shape>(object([("dot", Value::default()), ("label", string(""))]))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,531 @@
+use super::Error;
+use prost_types::value::Kind;
+use prost_types::{ListValue, NullValue, Struct, Value};
+use serde::ser::{self, Serialize};
+use std::collections::BTreeMap;
+
+/// Serializes Rust types into a `Value`.
+pub struct Serializer;
+
+fn value(kind: Kind) -> Value {
+    Value { kind: Some(kind) }
+}
+
+fn null() -> Value {
+    value(Kind::NullValue(NullValue::NullValue.into()))
+}
+
+fn single(key: &str, inner: Value) -> Value {
+    let mut fields = BTreeMap::new();
+
+    fields.insert(key.to_string(), inner);
+
+    value(Kind::StructValue(Struct { fields }))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = Error;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(value(Kind::BoolValue(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    /// Integers outside of the range `f64` can represent exactly are serialized as strings, this
+    /// is mostly relevant for snowflakes.
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        if v.unsigned_abs() < 1 << f64::MANTISSA_DIGITS {
+            self.serialize_f64(v as f64)
+        } else {
+            self.serialize_str(&v.to_string())
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        if v < 1 << f64::MANTISSA_DIGITS {
+            self.serialize_f64(v as f64)
+        } else {
+            self.serialize_str(&v.to_string())
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(value(Kind::NumberValue(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(value(Kind::StringValue(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        let values = v
+            .iter()
+            .map(|byte| value(Kind::NumberValue((*byte).into())));
+
+        Ok(value(Kind::ListValue(ListValue {
+            values: values.collect(),
+        })))
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(null())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> Result<Value, Error> {
+        v.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(null())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        v: &T,
+    ) -> Result<Value, Error> {
+        v.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        v: &T,
+    ) -> Result<Value, Error> {
+        Ok(single(variant, v.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeSeq, Error> {
+        Ok(SerializeSeq {
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeSeq, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeSeq, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeTupleVariant, Error> {
+        Ok(SerializeTupleVariant {
+            variant,
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            fields: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeStructVariant, Error> {
+        Ok(SerializeStructVariant {
+            variant,
+            fields: BTreeMap::new(),
+        })
+    }
+}
+
+pub struct SerializeSeq {
+    values: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SerializeSeq {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        let index = self.values.len();
+
+        self.values.push(
+            v.serialize(Serializer)
+                .map_err(|error| error.within(index))?,
+        );
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(value(Kind::ListValue(ListValue {
+            values: self.values,
+        })))
+    }
+}
+
+impl ser::SerializeTuple for SerializeSeq {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, v)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, v)
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeTupleVariant {
+    variant: &'static str,
+    values: Vec<Value>,
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        let index = self.values.len();
+
+        self.values.push(
+            v.serialize(Serializer)
+                .map_err(|error| error.within(index).within(self.variant))?,
+        );
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        let values = value(Kind::ListValue(ListValue {
+            values: self.values,
+        }));
+
+        Ok(single(self.variant, values))
+    }
+}
+
+pub struct SerializeMap {
+    fields: BTreeMap<String, Value>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = match key.serialize(Serializer)?.kind {
+            Some(Kind::StringValue(key)) => key,
+            Some(Kind::NumberValue(key)) => key.to_string(),
+            Some(Kind::BoolValue(key)) => key.to_string(),
+            _ => {
+                return Err(Error::custom(
+                    "map keys must be strings, numbers or booleans",
+                ));
+            }
+        };
+
+        self.key = Some(key);
+
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::custom("value serialized before key"))?;
+
+        let v = v
+            .serialize(Serializer)
+            .map_err(|error| error.within(&key))?;
+
+        self.fields.insert(key, v);
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(value(Kind::StructValue(Struct {
+            fields: self.fields,
+        })))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<(), Error> {
+        let v = v.serialize(Serializer).map_err(|error| error.within(key))?;
+
+        self.fields.insert(key.to_string(), v);
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+pub struct SerializeStructVariant {
+    variant: &'static str,
+    fields: BTreeMap<String, Value>,
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<(), Error> {
+        let v = v
+            .serialize(Serializer)
+            .map_err(|error| error.within(key).within(self.variant))?;
+
+        self.fields.insert(key.to_string(), v);
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        let fields = value(Kind::StructValue(Struct {
+            fields: self.fields,
+        }));
+
+        Ok(single(self.variant, fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value;
+    use prost_types::Value;
+    use prost_types::value::Kind;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use twilight_model::id::Id;
+    use twilight_model::id::marker::{ChannelMarker, MessageMarker};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Shape {
+        Dot,
+        Label(String),
+        Circle { radius: f64 },
+        Line(u32, u32),
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Everything {
+        flag: bool,
+        small: i8,
+        count: u32,
+        negative: i64,
+        large: u64,
+        ratio: f64,
+        name: String,
+        letter: char,
+        missing: Option<String>,
+        present: Option<u16>,
+        list: Vec<u8>,
+        map: BTreeMap<String, i32>,
+        shapes: Vec<Shape>,
+        channel_id: Id<ChannelMarker>,
+        message_ids: Vec<Id<MessageMarker>>,
+        unit: (),
+    }
+
+    fn kind(value: Value) -> Kind {
+        value.kind.expect("values have a kind")
+    }
+
+    #[test]
+    fn round_trip() {
+        let everything = Everything {
+            flag: true,
+            small: -8,
+            count: 7,
+            negative: -(1 << 60),
+            large: u64::MAX,
+            ratio: 0.25,
+            name: String::from("ari"),
+            letter: 'é',
+            missing: None,
+            present: Some(3),
+            list: vec![1, 2, 3],
+            map: BTreeMap::from([(String::from("a"), 1), (String::from("b"), -2)]),
+            shapes: vec![
+                Shape::Dot,
+                Shape::Label(String::from("hi")),
+                Shape::Circle { radius: 1.5 },
+                Shape::Line(1, 2),
+            ],
+            channel_id: Id::new(1_234_567_890_123_456_789),
+            message_ids: vec![Id::new(1), Id::new(u64::MAX)],
+            unit: (),
+        };
+
+        let value = value::to_struct(&everything).unwrap();
+
+        assert_eq!(value::from_struct::<Everything>(value), Ok(everything));
+    }
+
+    #[test]
+    fn large_integers() {
+        let exact = (1_u64 << 53) - 1;
+
+        assert_eq!(
+            kind(value::to_value(&exact).unwrap()),
+            Kind::NumberValue(exact as f64)
+        );
+        assert_eq!(
+            kind(value::to_value(&(1_u64 << 53)).unwrap()),
+            Kind::StringValue(String::from("9007199254740992"))
+        );
+        assert_eq!(
+            kind(value::to_value(&u64::MAX).unwrap()),
+            Kind::StringValue(u64::MAX.to_string())
+        );
+        assert_eq!(
+            kind(value::to_value(&-(1_i64 << 53)).unwrap()),
+            Kind::StringValue(String::from("-9007199254740992"))
+        );
+        assert_eq!(
+            kind(value::to_value(&-5_i64).unwrap()),
+            Kind::NumberValue(-5.0)
+        );
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(
+            kind(value::to_value(&Shape::Dot).unwrap()),
+            Kind::StringValue(String::from("dot"))
+        );
+
+        let Kind::StructValue(label) =
+            kind(value::to_value(&Shape::Label(String::from("hi"))).unwrap())
+        else {
+            panic!("newtype variants serialize to an object");
+        };
+
+        assert_eq!(
+            label.fields.get("label").cloned().map(kind),
+            Some(Kind::StringValue(String::from("hi")))
+        );
+
+        let Kind::StructValue(circle) =
+            kind(value::to_value(&Shape::Circle { radius: 2.0 }).unwrap())
+        else {
+            panic!("struct variants serialize to an object");
+        };
+
+        let Some(Kind::StructValue(fields)) = circle.fields.get("circle").cloned().map(kind) else {
+            panic!("struct variant fields serialize to an object");
+        };
+
+        assert_eq!(
+            fields.fields.get("radius").cloned().map(kind),
+            Some(Kind::NumberValue(2.0))
+        );
+    }
+
+    #[test]
+    fn to_struct_rejects_non_objects() {
+        assert!(value::to_struct(&1_u32).is_err());
+        assert!(value::to_struct(&Shape::Dot).is_err());
+        assert!(value::to_struct(&Shape::Line(1, 2)).is_ok());
+    }
+}
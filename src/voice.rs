@@ -0,0 +1,146 @@
+//! Talking with members of voice channels through a realtime session per channel.
+//!
+//! Voice connections are made through a [`VoiceBackend`], so sessions can be driven by a
+//! stand-in voice server in tests as well as by Discord.
+
+use self::session::VoiceSession;
+use crate::State;
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::info;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker};
+
+pub use self::discord::DiscordVoice;
+
+mod discord;
+#[cfg(test)]
+mod loopback;
+mod session;
+
+/// Sample rate of audio received from voice channels, as the Live API expects it.
+pub const INPUT_SAMPLE_RATE: u32 = 16_000;
+
+/// Samples in each [`Tick`], 20 ms like a Discord voice packet.
+pub const TICK_SAMPLES: usize = INPUT_SAMPLE_RATE as usize / 50;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct VoiceOptions {
+    /// Let the model join voice channels, which needs the `GUILD_VOICE_STATES` intent.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Connects to voice channels.
+pub trait VoiceBackend: Send + Sync {
+    /// Join `channel_id`, moving over if already connected elsewhere in the guild.
+    fn join(
+        &self,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) -> BoxFuture<'_, anyhow::Result<VoiceConnection>>;
+
+    /// Disconnect from the voice channel in `guild_id`.
+    fn leave(&self, guild_id: Id<GuildMarker>) -> BoxFuture<'_, anyhow::Result<()>>;
+}
+
+/// A joined voice channel.
+pub struct VoiceConnection {
+    /// A tick of audio for every 20 ms connected, including silence.
+    pub audio: UnboundedReceiver<Tick>,
+    pub speaker: Box<dyn Speaker>,
+}
+
+/// 20 ms of audio received from a voice channel.
+#[derive(Clone, Debug)]
+pub struct Tick {
+    /// Every speaking member mixed together, 16 bit mono PCM at [`INPUT_SAMPLE_RATE`].
+    pub samples: Vec<i16>,
+    /// Whether anyone was speaking, otherwise the samples are silence.
+    pub speaking: bool,
+}
+
+/// Plays audio in a voice channel.
+pub trait Speaker: Send + Sync {
+    /// Queue 16 bit mono PCM to be played after anything already queued.
+    fn play(&self, samples: Vec<i16>, sample_rate: u32) -> BoxFuture<'_, anyhow::Result<()>>;
+
+    /// Stop playing and drop everything queued.
+    fn stop(&self) -> BoxFuture<'_, anyhow::Result<()>>;
+}
+
+/// The voice session of every guild, a guild has at most one voice connection at a time.
+pub struct Voice {
+    backend: Arc<dyn VoiceBackend>,
+    sessions: Mutex<HashMap<Id<GuildMarker>, VoiceSession>>,
+}
+
+impl Voice {
+    pub fn new(backend: Arc<dyn VoiceBackend>) -> Self {
+        Self {
+            backend,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Join `channel_id` and start a session talking in it, replacing any session in the guild.
+    pub async fn join(
+        &self,
+        state: &State,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) -> anyhow::Result<()> {
+        // stopped before joining, so it does not leave the channel it is moved to
+        self.remove(guild_id);
+
+        let connection = self.backend.join(guild_id, channel_id).await?;
+
+        info!("joined voice channel_id={channel_id}, start voice session");
+
+        let session = VoiceSession::spawn(
+            state,
+            self.backend.clone(),
+            guild_id,
+            channel_id,
+            connection,
+        );
+
+        self.sessions
+            .lock()
+            .expect("voice sessions are not poisoned")
+            .insert(guild_id, session);
+
+        Ok(())
+    }
+
+    /// Stop the session in `guild_id` and leave its voice channel.
+    pub async fn leave(&self, guild_id: Id<GuildMarker>) -> anyhow::Result<()> {
+        self.remove(guild_id);
+        self.backend.leave(guild_id).await
+    }
+
+    /// The voice channel ari is talking in within `guild_id`.
+    pub fn channel_id(&self, guild_id: Id<GuildMarker>) -> Option<Id<ChannelMarker>> {
+        self.sessions
+            .lock()
+            .expect("voice sessions are not poisoned")
+            .get(&guild_id)
+            .filter(|session| !session.is_closed())
+            .map(VoiceSession::channel_id)
+    }
+
+    /// Stop the session in `guild_id`, staying in its voice channel.
+    fn remove(&self, guild_id: Id<GuildMarker>) {
+        let mut sessions = self
+            .sessions
+            .lock()
+            .expect("voice sessions are not poisoned");
+
+        // sessions that went quiet or lost their call have left their channel already
+        sessions.retain(|_guild_id, session| !session.is_closed());
+        sessions.remove(&guild_id);
+    }
+}
@@ -0,0 +1,150 @@
+use super::{Speaker, TICK_SAMPLES, Tick, VoiceBackend, VoiceConnection};
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use songbird::driver::{Channels, DecodeConfig, DecodeMode, SampleRate};
+use songbird::input::{Input, RawAdapter};
+use songbird::shards::TwilightMap;
+use songbird::{Call, Config, CoreEvent, Event, EventContext, EventHandler, Songbird};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use twilight_gateway::MessageSender;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, UserMarker};
+
+/// Voice connections through the Discord voice gateway.
+pub struct DiscordVoice {
+    songbird: Songbird,
+}
+
+impl DiscordVoice {
+    /// Connect through the shards whose senders are given by shard id, as `user_id`.
+    pub fn new(senders: HashMap<u32, MessageSender>, user_id: Id<UserMarker>) -> Self {
+        let songbird = Songbird::twilight(Arc::new(TwilightMap::new(senders)), user_id);
+
+        // decoded straight into what the Live API expects
+        songbird.set_config(
+            Config::default().decode_mode(DecodeMode::Decode(DecodeConfig::new(
+                Channels::Mono,
+                SampleRate::Hz16000,
+            ))),
+        );
+
+        Self { songbird }
+    }
+
+    /// Hand voice state and voice server updates to the connections.
+    pub async fn process(&self, event: &twilight_gateway::Event) {
+        self.songbird.process(event).await;
+    }
+}
+
+impl VoiceBackend for DiscordVoice {
+    fn join(
+        &self,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) -> BoxFuture<'_, anyhow::Result<VoiceConnection>> {
+        Box::pin(async move {
+            let call = self.songbird.join(guild_id, channel_id).await?;
+            let (sender, audio) = mpsc::unbounded_channel();
+
+            {
+                let mut call = call.lock().await;
+
+                // still registered when moving from another channel in the guild
+                call.remove_all_global_events();
+                call.add_global_event(CoreEvent::VoiceTick.into(), Receiver { sender });
+            }
+
+            Ok(VoiceConnection {
+                audio,
+                speaker: Box::new(DiscordSpeaker { call }),
+            })
+        })
+    }
+
+    fn leave(&self, guild_id: Id<GuildMarker>) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.songbird.remove(guild_id).await?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Mixes the decoded audio of every speaking member.
+struct Receiver {
+    sender: UnboundedSender<Tick>,
+}
+
+#[async_trait]
+impl EventHandler for Receiver {
+    async fn act(&self, context: &EventContext<'_>) -> Option<Event> {
+        let EventContext::VoiceTick(tick) = context else {
+            return None;
+        };
+
+        // silence is passed on as well, the model only notices someone stopped speaking by it
+        let mut mixed = vec![0_i16; TICK_SAMPLES];
+        let mut speaking = false;
+
+        for decoded in tick
+            .speaking
+            .values()
+            .filter_map(|voice| voice.decoded_voice.as_ref())
+        {
+            if mixed.len() < decoded.len() {
+                mixed.resize(decoded.len(), 0_i16);
+            }
+
+            for (mixed, sample) in mixed.iter_mut().zip(decoded) {
+                *mixed = mixed.saturating_add(*sample);
+            }
+
+            speaking = true;
+        }
+
+        let tick = Tick {
+            samples: mixed,
+            speaking,
+        };
+
+        match self.sender.send(tick) {
+            Ok(()) => None,
+            Err(_error) => Some(Event::Cancel),
+        }
+    }
+}
+
+struct DiscordSpeaker {
+    call: Arc<Mutex<Call>>,
+}
+
+impl Speaker for DiscordSpeaker {
+    fn play(&self, samples: Vec<i16>, sample_rate: u32) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            // raw input is read as 32 bit floats
+            let bytes = samples
+                .iter()
+                .flat_map(|sample| (f32::from(*sample) / 32768.0).to_le_bytes())
+                .collect::<Vec<_>>();
+
+            let input = Input::from(RawAdapter::new(Cursor::new(bytes), sample_rate, 1));
+
+            self.call.lock().await.enqueue_input(input).await;
+
+            Ok(())
+        })
+    }
+
+    fn stop(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.call.lock().await.queue().stop();
+
+            Ok(())
+        })
+    }
+}
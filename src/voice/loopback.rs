@@ -0,0 +1,181 @@
+//! A stand-in voice server, where members say Ogg Opus fixtures and playback is recorded, and a
+//! stand-in Live API stream.
+
+use super::{Speaker, TICK_SAMPLES, Tick, VoiceBackend, VoiceConnection};
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{
+    BidiGenerateContentClientMessage, BidiGenerateContentServerMessage,
+};
+use crate::media::audio;
+use crate::session::Connection;
+use futures_util::StreamExt;
+use futures_util::future::{self, BoxFuture};
+use futures_util::stream;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tonic::Status;
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker};
+
+/// Ticks of silence after each fixture, half a second.
+pub const PAUSE: usize = 25;
+
+/// What happened on the stand-in server.
+#[derive(Debug, Default)]
+pub struct Recording {
+    pub joined: Vec<Id<ChannelMarker>>,
+    pub left: Vec<Id<GuildMarker>>,
+    /// Audio played, along with its sample rate.
+    pub played: Vec<(Vec<i16>, u32)>,
+    /// How often playback was stopped.
+    pub stops: usize,
+}
+
+pub struct LoopbackVoice {
+    /// Said by the members of every channel joined.
+    fixtures: Vec<Vec<u8>>,
+    /// Keeps the audio of each guild's connection open until leaving.
+    senders: Mutex<HashMap<Id<GuildMarker>, UnboundedSender<Tick>>>,
+    recording: Arc<Mutex<Recording>>,
+}
+
+impl LoopbackVoice {
+    pub fn new(fixtures: Vec<Vec<u8>>) -> Self {
+        Self {
+            fixtures,
+            senders: Mutex::new(HashMap::new()),
+            recording: Arc::default(),
+        }
+    }
+
+    pub fn recording(&self) -> Arc<Mutex<Recording>> {
+        self.recording.clone()
+    }
+
+    fn join_now(
+        &self,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) -> anyhow::Result<VoiceConnection> {
+        let (sender, audio) = mpsc::unbounded_channel();
+
+        for fixture in &self.fixtures {
+            let converted = audio::convert(fixture, Duration::from_secs(60))?;
+
+            for samples in audio::samples(&converted.blob.data).chunks(TICK_SAMPLES) {
+                let mut samples = samples.to_vec();
+                samples.resize(TICK_SAMPLES, 0);
+
+                sender.send(Tick {
+                    samples,
+                    speaking: true,
+                })?;
+            }
+
+            for _tick in 0..PAUSE {
+                sender.send(Tick {
+                    samples: vec![0; TICK_SAMPLES],
+                    speaking: false,
+                })?;
+            }
+        }
+
+        self.senders
+            .lock()
+            .expect("loopback senders are not poisoned")
+            .insert(guild_id, sender);
+
+        self.recording
+            .lock()
+            .expect("loopback recording is not poisoned")
+            .joined
+            .push(channel_id);
+
+        Ok(VoiceConnection {
+            audio,
+            speaker: Box::new(LoopbackSpeaker {
+                recording: self.recording.clone(),
+            }),
+        })
+    }
+}
+
+impl VoiceBackend for LoopbackVoice {
+    fn join(
+        &self,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+    ) -> BoxFuture<'_, anyhow::Result<VoiceConnection>> {
+        Box::pin(future::ready(self.join_now(guild_id, channel_id)))
+    }
+
+    fn leave(&self, guild_id: Id<GuildMarker>) -> BoxFuture<'_, anyhow::Result<()>> {
+        self.senders
+            .lock()
+            .expect("loopback senders are not poisoned")
+            .remove(&guild_id);
+
+        self.recording
+            .lock()
+            .expect("loopback recording is not poisoned")
+            .left
+            .push(guild_id);
+
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+struct LoopbackSpeaker {
+    recording: Arc<Mutex<Recording>>,
+}
+
+impl Speaker for LoopbackSpeaker {
+    fn play(&self, samples: Vec<i16>, sample_rate: u32) -> BoxFuture<'_, anyhow::Result<()>> {
+        self.recording
+            .lock()
+            .expect("loopback recording is not poisoned")
+            .played
+            .push((samples, sample_rate));
+
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn stop(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        self.recording
+            .lock()
+            .expect("loopback recording is not poisoned")
+            .stops += 1;
+
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+/// The server's end of a [`Connection`].
+pub struct LoopbackLive {
+    /// What the session sent after its setup was accepted.
+    pub requests: UnboundedReceiver<BidiGenerateContentClientMessage>,
+    /// What the session receives, dropping this closes the stream.
+    pub responses: UnboundedSender<Result<BidiGenerateContentServerMessage, Status>>,
+}
+
+impl LoopbackLive {
+    /// A connection whose setup was accepted, along with the server's end of it.
+    pub fn connect() -> (Connection, Self) {
+        let (sender, requests) = mpsc::unbounded_channel();
+        let (responses, mut receiver) = mpsc::unbounded_channel();
+
+        let connection = Connection {
+            sender,
+            stream: stream::poll_fn(move |context| receiver.poll_recv(context)).boxed(),
+        };
+
+        (
+            connection,
+            Self {
+                requests,
+                responses,
+            },
+        )
+    }
+}
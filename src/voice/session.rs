@@ -0,0 +1,603 @@
+use super::{INPUT_SAMPLE_RATE, Tick, VoiceBackend, VoiceConnection};
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::bidi_generate_content_client_message::MessageType as ClientMessageType;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::bidi_generate_content_server_message::MessageType;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::generation_config::Modality;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::part::Data;
+use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{
+    BidiGenerateContentClientMessage, BidiGenerateContentRealtimeInput,
+    BidiGenerateContentServerContent, BidiGenerateContentServerMessage, BidiGenerateContentSetup,
+    Blob,
+};
+use crate::media::audio;
+use crate::session::{self, Backoff, Connection, OUTPUT_SAMPLE_RATE};
+use crate::{State, render};
+use futures_util::TryStreamExt;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot::{self, Receiver, Sender};
+use tokio::time::{self, Instant};
+use tracing::{debug, info, warn};
+use twilight_model::id::Id;
+use twilight_model::id::marker::{ChannelMarker, GuildMarker};
+
+/// Samples of member audio sent per realtime input message, 100 ms.
+const INPUT_CHUNK: usize = INPUT_SAMPLE_RATE as usize / 10;
+
+/// Seconds of model audio queued for playback at once, shorter pieces have audible gaps.
+const OUTPUT_CHUNK: u32 = 1;
+
+/// Handle to a running voice session, which stops once dropped.
+pub struct VoiceSession {
+    channel_id: Id<ChannelMarker>,
+    stop: Sender<()>,
+}
+
+/// Why [`run`] returned without an error.
+enum Stop {
+    Idle,
+    Left,
+    /// The voice connection was closed from the other end.
+    Disconnected,
+}
+
+impl VoiceSession {
+    /// Spawn a driver task talking in the joined `channel_id`.
+    pub fn spawn(
+        state: &State,
+        backend: Arc<dyn VoiceBackend>,
+        guild_id: Id<GuildMarker>,
+        channel_id: Id<ChannelMarker>,
+        connection: VoiceConnection,
+    ) -> Self {
+        let setup = setup(state, channel_id);
+        let api_key = state.options.gemini.api_key.clone();
+        let idle_timeout = Duration::from_secs(state.options.gemini.idle_timeout);
+
+        let (stop, stop_receiver) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let connect = || Connection::open(api_key.clone(), setup.clone());
+
+            let leave = match run(connect, idle_timeout, connection, stop_receiver).await {
+                Ok(Stop::Idle) => {
+                    info!("voice session in channel_id={channel_id} is idle, leave");
+
+                    true
+                }
+                Ok(Stop::Left) => false,
+                Ok(Stop::Disconnected) => {
+                    info!("voice connection in channel_id={channel_id} closed, leave");
+
+                    true
+                }
+                Err(error) => {
+                    warn!("voice session in channel_id={channel_id} ended: {error}");
+
+                    true
+                }
+            };
+
+            if leave && let Err(error) = backend.leave(guild_id).await {
+                warn!("failed to leave voice channel_id={channel_id}: {error}");
+            }
+        });
+
+        Self { channel_id, stop }
+    }
+
+    pub fn channel_id(&self) -> Id<ChannelMarker> {
+        self.channel_id
+    }
+
+    /// Whether the session ended on its own, nobody having spoken for a while or the call having
+    /// dropped, after which it has left the voice channel.
+    pub fn is_closed(&self) -> bool {
+        self.stop.is_closed()
+    }
+}
+
+/// The text session's setup, responding with audio and without tools, which need a turn.
+fn setup(state: &State, channel_id: Id<ChannelMarker>) -> BidiGenerateContentSetup {
+    let description = format!(
+        "a voice call in #{} (channel_id={channel_id}), you hear the members speaking and your replies are spoken aloud",
+        render::channel_name(state, channel_id),
+    );
+
+    let mut setup = session::setup(state, &description);
+
+    setup.tools.clear();
+
+    if let Some(generation_config) = &mut setup.generation_config {
+        generation_config.response_modalities.clear();
+        generation_config.push_response_modalities(Modality::Audio);
+    }
+
+    setup
+}
+
+/// Talk in the voice channel, reconnecting through `connect` whenever the stream fails.
+async fn run<F>(
+    mut connect: impl FnMut() -> F,
+    idle_timeout: Duration,
+    connection: VoiceConnection,
+    mut stop: Receiver<()>,
+) -> anyhow::Result<Stop>
+where
+    F: Future<Output = anyhow::Result<Connection>>,
+{
+    let mut call = Call::new(connection);
+    let mut backoff = Backoff::default();
+
+    loop {
+        match connect().await {
+            Ok(live) => {
+                backoff.reset();
+
+                match call.talk(live, idle_timeout, &mut stop).await {
+                    Ok(stop) => return Ok(stop),
+                    Err(error) => warn!("lost voice session: {error}"),
+                }
+            }
+            Err(error) => warn!("failed to connect voice session: {error}"),
+        }
+
+        // the model's audio so far is from a turn the new stream knows nothing about
+        call.output.clear();
+
+        tokio::select! {
+            _ = &mut stop => return Ok(Stop::Left),
+            () = backoff.wait() => {}
+        }
+    }
+}
+
+/// Audio passed between a voice channel and the model, which survives reconnecting.
+struct Call {
+    connection: VoiceConnection,
+    /// Member audio not sent yet.
+    input: Vec<i16>,
+    /// Whether anyone was speaking in the last tick.
+    speaking: bool,
+    /// When a member last spoke, the call is idle some time after.
+    last_speech: Instant,
+    /// Model audio not played yet.
+    output: Vec<i16>,
+    output_sample_rate: u32,
+}
+
+impl Call {
+    fn new(connection: VoiceConnection) -> Self {
+        Self {
+            connection,
+            input: Vec::with_capacity(INPUT_CHUNK),
+            speaking: false,
+            last_speech: Instant::now(),
+            output: Vec::new(),
+            output_sample_rate: OUTPUT_SAMPLE_RATE,
+        }
+    }
+
+    /// Pass audio both ways until idle, left or either side fails.
+    async fn talk(
+        &mut self,
+        mut live: Connection,
+        idle_timeout: Duration,
+        stop: &mut Receiver<()>,
+    ) -> anyhow::Result<Stop> {
+        loop {
+            // silence keeps arriving, only speech counts as activity
+            let deadline = self.last_speech + idle_timeout;
+
+            tokio::select! {
+                _ = &mut *stop => return Ok(Stop::Left),
+                _ = time::sleep_until(deadline) => return Ok(Stop::Idle),
+                tick = self.connection.audio.recv() => {
+                    let Some(tick) = tick else {
+                        return Ok(Stop::Disconnected);
+                    };
+
+                    if let Some(message) = self.hear(tick) {
+                        live.sender.send(message)?;
+                    }
+                }
+                message = live.stream.try_next() => {
+                    let Some(BidiGenerateContentServerMessage { message_type }) = message? else {
+                        anyhow::bail!("stream closed by server");
+                    };
+
+                    match message_type {
+                        Some(MessageType::ServerContent(server_content)) => {
+                            self.speak(server_content).await?;
+                        }
+                        message_type => debug!("{message_type:?}"),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Buffer member audio, returning the realtime input to send once a chunk is complete or the
+    /// members went quiet.
+    fn hear(&mut self, tick: Tick) -> Option<BidiGenerateContentClientMessage> {
+        // the end of what was said goes out right away, for the model to notice it ended
+        let quiet = self.speaking && !tick.speaking;
+
+        if tick.speaking {
+            self.last_speech = Instant::now();
+        }
+
+        self.speaking = tick.speaking;
+        self.input.extend_from_slice(&tick.samples);
+
+        if self.input.len() < INPUT_CHUNK && !quiet {
+            return None;
+        }
+
+        let data = mem::take(&mut self.input)
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+
+        Some(session::client_message(ClientMessageType::RealtimeInput(
+            BidiGenerateContentRealtimeInput {
+                media_chunks: vec![Blob {
+                    mime_type: format!("audio/pcm;rate={INPUT_SAMPLE_RATE}"),
+                    data,
+                }],
+            },
+        )))
+    }
+
+    /// Play the model's audio, or stop playing it when a member speaks over the model.
+    async fn speak(
+        &mut self,
+        BidiGenerateContentServerContent {
+            model_turn,
+            turn_complete,
+            interrupted,
+            ..
+        }: BidiGenerateContentServerContent,
+    ) -> anyhow::Result<()> {
+        // whatever the model was still going to say is stale
+        if interrupted {
+            debug!("model interrupted, stop playback");
+
+            self.output.clear();
+
+            return self.connection.speaker.stop().await;
+        }
+
+        let blobs = model_turn
+            .into_iter()
+            .flat_map(|content| content.parts)
+            .filter_map(|part| match part.data {
+                Some(Data::InlineData(blob)) if blob.mime_type.starts_with("audio/pcm") => {
+                    Some(blob)
+                }
+                _ => None,
+            });
+
+        for blob in blobs {
+            self.output_sample_rate =
+                audio::sample_rate(&blob.mime_type).unwrap_or(OUTPUT_SAMPLE_RATE);
+            self.output.extend(audio::samples(&blob.data));
+        }
+
+        let full = self.output.len() >= (self.output_sample_rate * OUTPUT_CHUNK) as usize;
+
+        if (full || turn_complete) && !self.output.is_empty() {
+            self.connection
+                .speaker
+                .play(mem::take(&mut self.output), self.output_sample_rate)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Call, INPUT_CHUNK, Stop, run};
+    use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::bidi_generate_content_client_message::MessageType as ClientMessageType;
+    use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::bidi_generate_content_server_message::MessageType;
+    use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::part::Data;
+    use crate::gemini::googleapis::google::ai::generativelanguage::v1alpha::{
+        BidiGenerateContentServerContent, BidiGenerateContentServerMessage, Blob, Content, Part,
+    };
+    use crate::session::Connection;
+    use crate::voice::loopback::{LoopbackLive, LoopbackVoice, PAUSE};
+    use crate::voice::{TICK_SAMPLES, VoiceBackend};
+    use anyhow::Context;
+    use futures_util::future::{self, Ready};
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+    use tokio::time::{self, Instant};
+    use tonic::Status;
+    use twilight_model::id::Id;
+
+    /// A voice message laid out the way Discord records them, Ogg Opus at 48 kHz in 20 ms packets
+    /// with a pre-skip of 312. Every packet is Discord's silence frame, and the final granule
+    /// position ends the message 10 ms into the last one, 990 ms in all.
+    const FIXTURE: &[u8] = include_bytes!("fixtures/voice-message.ogg");
+
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Model output holding `samples` at 24 kHz.
+    fn server_content(
+        samples: &[i16],
+        turn_complete: bool,
+        interrupted: bool,
+    ) -> BidiGenerateContentServerContent {
+        let parts = (!samples.is_empty())
+            .then(|| Part {
+                data: Some(Data::InlineData(Blob {
+                    mime_type: String::from("audio/pcm;rate=24000"),
+                    data: samples
+                        .iter()
+                        .flat_map(|sample| sample.to_le_bytes())
+                        .collect(),
+                })),
+            })
+            .into_iter()
+            .collect();
+
+        BidiGenerateContentServerContent {
+            model_turn: Some(Content {
+                parts,
+                role: String::from("model"),
+            }),
+            turn_complete,
+            interrupted,
+            ..Default::default()
+        }
+    }
+
+    fn server_message(
+        server_content: BidiGenerateContentServerContent,
+    ) -> Result<BidiGenerateContentServerMessage, Status> {
+        Ok(BidiGenerateContentServerMessage {
+            message_type: Some(MessageType::ServerContent(server_content)),
+        })
+    }
+
+    async fn call(backend: &LoopbackVoice) -> Call {
+        Call::new(backend.join(Id::new(1), Id::new(2)).await.unwrap())
+    }
+
+    /// Hands out `connections` in order, failing to connect once they run out.
+    fn connector(
+        connections: Vec<Connection>,
+    ) -> impl FnMut() -> Ready<anyhow::Result<Connection>> {
+        let mut connections = connections.into_iter();
+
+        move || future::ready(connections.next().context("no connections left"))
+    }
+
+    #[tokio::test]
+    async fn join() {
+        let backend = LoopbackVoice::new(vec![FIXTURE.to_vec(), FIXTURE.to_vec()]);
+        let mut connection = backend.join(Id::new(1), Id::new(2)).await.unwrap();
+
+        let mut speaking = 0;
+        let mut silent = 0;
+
+        while let Ok(tick) = connection.audio.try_recv() {
+            assert_eq!(tick.samples.len(), TICK_SAMPLES);
+
+            if tick.speaking {
+                speaking += 1;
+            } else {
+                silent += 1;
+            }
+        }
+
+        // 990 ms each, the last tick padded
+        assert_eq!(speaking, 2 * 50);
+        assert_eq!(silent, 2 * PAUSE);
+
+        backend.leave(Id::new(1)).await.unwrap();
+
+        assert!(connection.audio.recv().await.is_none());
+
+        let recording = backend.recording();
+        let recording = recording.lock().unwrap();
+
+        assert_eq!(recording.joined, [Id::new(2)]);
+        assert_eq!(recording.left, [Id::new(1)]);
+    }
+
+    #[tokio::test]
+    async fn input_chunks() {
+        let backend = LoopbackVoice::new(vec![FIXTURE.to_vec()]);
+        let mut call = call(&backend).await;
+
+        let mut ticks = Vec::new();
+
+        while let Ok(tick) = call.connection.audio.try_recv() {
+            ticks.push(tick);
+        }
+
+        let heard = ticks.iter().map(|tick| tick.samples.len()).sum::<usize>();
+        let quiet = ticks.iter().position(|tick| !tick.speaking).unwrap();
+        let mut sent = 0;
+
+        for (index, tick) in ticks.into_iter().enumerate() {
+            let Some(chunk) = call.hear(tick) else {
+                // the tail of what was said is not held back until the next chunk fills up
+                assert_ne!(index, quiet);
+
+                continue;
+            };
+
+            let Some(ClientMessageType::RealtimeInput(realtime_input)) = chunk.message_type else {
+                panic!("not realtime input: {chunk:?}");
+            };
+
+            let [blob] = realtime_input.media_chunks.as_slice() else {
+                panic!("not a single chunk: {realtime_input:?}");
+            };
+
+            assert_eq!(blob.mime_type, "audio/pcm;rate=16000");
+
+            if index != quiet {
+                assert_eq!(blob.data.len(), INPUT_CHUNK * 2);
+            }
+
+            sent += blob.data.len() / 2;
+        }
+
+        // silence is sent as well
+        assert_eq!(sent + call.input.len(), heard);
+        assert!(call.input.len() < INPUT_CHUNK);
+    }
+
+    #[tokio::test]
+    async fn playback() {
+        let backend = LoopbackVoice::new(Vec::new());
+        let recording = backend.recording();
+        let mut call = call(&backend).await;
+
+        // held back until a second has accumulated
+        call.speak(server_content(&[1; 12_000], false, false))
+            .await
+            .unwrap();
+
+        assert!(recording.lock().unwrap().played.is_empty());
+
+        call.speak(server_content(&[2; 12_000], false, false))
+            .await
+            .unwrap();
+        call.speak(server_content(&[3; 100], false, false))
+            .await
+            .unwrap();
+        call.speak(server_content(&[], true, false)).await.unwrap();
+
+        let recording = recording.lock().unwrap();
+        let played = recording
+            .played
+            .iter()
+            .map(|(samples, sample_rate)| (samples.len(), *sample_rate))
+            .collect::<Vec<_>>();
+
+        assert_eq!(played, [(24_000, 24_000), (100, 24_000)]);
+        assert_eq!(recording.played[0].0[12_000], 2);
+        assert_eq!(recording.stops, 0);
+    }
+
+    #[tokio::test]
+    async fn barge_in() {
+        let backend = LoopbackVoice::new(Vec::new());
+        let recording = backend.recording();
+        let mut call = call(&backend).await;
+
+        call.speak(server_content(&[1; 12_000], false, false))
+            .await
+            .unwrap();
+        call.speak(server_content(&[], false, true)).await.unwrap();
+
+        assert_eq!(recording.lock().unwrap().stops, 1);
+
+        // nothing of the interrupted turn is left to play
+        call.speak(server_content(&[], true, false)).await.unwrap();
+        call.speak(server_content(&[2; 100], true, false))
+            .await
+            .unwrap();
+
+        assert_eq!(recording.lock().unwrap().played, [(vec![2; 100], 24_000)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn talk() {
+        let backend = LoopbackVoice::new(vec![FIXTURE.to_vec()]);
+        let recording = backend.recording();
+        let connection = backend.join(Id::new(1), Id::new(2)).await.unwrap();
+        let (live, mut server) = LoopbackLive::connect();
+        let (_stop, stop) = oneshot::channel();
+
+        let start = Instant::now();
+        let run = tokio::spawn(run(connector(vec![live]), IDLE_TIMEOUT, connection, stop));
+
+        server
+            .responses
+            .send(server_message(server_content(&[1; 100], true, false)))
+            .unwrap();
+
+        let mut sent = 0;
+
+        // the stream is dropped once the call is idle
+        while let Some(message) = server.requests.recv().await {
+            let Some(ClientMessageType::RealtimeInput(realtime_input)) = message.message_type
+            else {
+                panic!("not realtime input: {message:?}");
+            };
+
+            sent += realtime_input
+                .media_chunks
+                .iter()
+                .map(|blob| blob.data.len() / 2)
+                .sum::<usize>();
+        }
+
+        assert!(matches!(run.await.unwrap(), Ok(Stop::Idle)));
+        assert!(start.elapsed() >= IDLE_TIMEOUT);
+
+        // everything heard, the pause included, apart from a chunk that never filled up
+        let heard = (50 + PAUSE) * TICK_SAMPLES;
+
+        assert!(sent <= heard && sent > heard - INPUT_CHUNK);
+        assert_eq!(recording.lock().unwrap().played, [(vec![1; 100], 24_000)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconnect() {
+        let backend = LoopbackVoice::new(Vec::new());
+        let recording = backend.recording();
+        let connection = backend.join(Id::new(1), Id::new(2)).await.unwrap();
+        let (first, first_server) = LoopbackLive::connect();
+        let (second, second_server) = LoopbackLive::connect();
+        let (stop, stop_receiver) = oneshot::channel();
+
+        let run = tokio::spawn(run(
+            connector(vec![first, second]),
+            IDLE_TIMEOUT,
+            connection,
+            stop_receiver,
+        ));
+
+        // cut off by the stream closing, never to be played
+        first_server
+            .responses
+            .send(server_message(server_content(&[1; 12_000], false, false)))
+            .unwrap();
+        drop(first_server);
+
+        second_server
+            .responses
+            .send(server_message(server_content(&[2; 100], true, false)))
+            .unwrap();
+
+        // past the backoff
+        time::sleep(Duration::from_secs(2)).await;
+
+        stop.send(()).unwrap();
+
+        assert!(matches!(run.await.unwrap(), Ok(Stop::Left)));
+        assert_eq!(recording.lock().unwrap().played, [(vec![2; 100], 24_000)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn disconnect() {
+        let backend = LoopbackVoice::new(Vec::new());
+        let connection = backend.join(Id::new(1), Id::new(2)).await.unwrap();
+        let (live, _server) = LoopbackLive::connect();
+        let (_stop, stop) = oneshot::channel();
+
+        let run = tokio::spawn(run(connector(vec![live]), IDLE_TIMEOUT, connection, stop));
+
+        backend.leave(Id::new(1)).await.unwrap();
+
+        assert!(matches!(run.await.unwrap(), Ok(Stop::Disconnected)));
+    }
+}